@@ -20,7 +20,7 @@ async def verify_jwt(request: Request, call_next):
         if not token:
             return JSONResponse(content={"message": "Token is missing"}, status_code=401)
 
-        decoded = jwt.decode(token, "test", algorithms=["HS256"])
+        decoded = jwt.decode(token, "test", algorithms=["HS256"], audience="authio", issuer="authio")
         print(decoded)
     except jwt.PyJWTError as e:
         return JSONResponse(content={"message": str(e)}, status_code=401)
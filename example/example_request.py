@@ -21,7 +21,7 @@ response = requests.post(login_url, json=login_data)
 
 # Check if the login was successful
 if response.status_code == 200:
-    token = response.text
+    token = response.json()["access_token"]
 
     print("Login successful")
     print("Received token: " + token)
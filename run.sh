@@ -30,6 +30,7 @@ docker run \
   -p 8080:8080 \
   --env-file .env \
   -e LDAP_URL=ldap://ldap:389 \
+  -e LDAP_ALLOW_PLAINTEXT=true \
   -e RUST_LOG=debug \
   -e RUST_BACKTRACE=1 \
   authio
@@ -1,18 +1,71 @@
+use crate::keys::is_symmetric;
 use dotenv::dotenv;
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
 use std::env;
+use std::str::FromStr;
 
 /// Public configuration struct
 pub struct Config {
-    pub jwt_secret_key: String,
+    pub jwt_secret_key: Option<String>,
+    pub jwt_previous_secret_keys: Vec<String>,
+    pub jwt_algorithm: Algorithm,
+    pub jwt_key_id: Option<String>,
+    pub jwt_private_key_path: Option<String>,
+    pub jwt_public_key_path: Option<String>,
     pub jwt_expiration_time_seconds: u64,
-    pub ldap_url: String,
+    pub jwt_issuer: String,
+    pub jwt_audience: String,
+    pub jwt_leeway_seconds: u64,
+    pub jwt_key_rotation_interval_seconds: Option<u64>,
+    pub refresh_token_expiration_time_seconds: u64,
+    pub ldap_urls: Vec<String>,
+    pub ldap_srv_domain: Option<String>,
+    pub ldap_srv_ldaps: bool,
+    pub ldap_server_selection: ServerSelection,
+    pub ldap_server_retry_seconds: u64,
+    pub ldap_connect_timeout_seconds: u64,
+    pub ldap_starttls: bool,
+    pub ldap_tls_ca_file: Option<String>,
+    pub ldap_tls_client_cert_file: Option<String>,
+    pub ldap_tls_client_key_file: Option<String>,
+    pub ldap_tls_verify_hostname: bool,
+    pub ldap_allow_plaintext: bool,
+    pub ldap_auth_mode: LdapAuthMode,
+    pub ldap_bind_dn: Option<String>,
+    pub ldap_bind_password: Option<String>,
+    pub ldap_user_search_base: String,
+    pub ldap_user_filter: String,
+    pub ldap_report_account_locked: bool,
+    pub ldap_group_lookup: GroupLookup,
+    pub ldap_group_filter: String,
+    pub ldap_nested_groups: NestedGroups,
+    pub ldap_group_search_base: Option<String>,
+    pub ldap_group_member_filter: String,
+    pub ldap_nested_groups_max_depth: usize,
+    pub ldap_group_descriptions: bool,
+    pub ldap_group_description_attr: String,
+    pub ldap_group_mapping_file: Option<String>,
+    pub ldap_permission_name: PermissionName,
+    pub ldap_permission_id: Option<PermissionId>,
+    pub permission_failure_policy: PermissionFailurePolicy,
+    pub ldap_pool_min_size: usize,
+    pub ldap_pool_max_size: usize,
+    pub ldap_pool_idle_timeout_seconds: u64,
+    pub ldap_pool_acquire_timeout_seconds: u64,
+    pub ldap_pool_health_check_interval_seconds: u64,
     pub http_bind_address: String,
     pub http_port: u16,
     pub ad_base_dn: String,
     pub ad_filter_format: String,
     pub ad_attrs: Vec<String>,
     pub jwt_company: String,
+    pub forward_auth_user_header: Option<String>,
+    pub forward_auth_company_header: Option<String>,
+    pub forward_auth_permissions_header: Option<String>,
+    pub admin_api_key: Option<String>,
+    pub introspection_api_key: Option<String>,
+    pub dummy_users_file: Option<String>,
 }
 
 /// Constructor for Config struct that loads the configuration from the environment
@@ -32,24 +85,434 @@ impl Config {
             .parse()
             .expect("JWT_EXPIRATION_TIME_SECONDS must be a number");
 
+        let jwt_algorithm: Algorithm = env::var("JWT_ALGORITHM")
+            .unwrap_or_else(|_| "HS256".to_string())
+            .parse()
+            .expect("JWT_ALGORITHM must be one of HS256, HS384, HS512, RS256, RS384, RS512, \
+            PS256, PS384, PS512, ES256, ES384 or EdDSA");
+
+        let jwt_key_rotation_interval_seconds: Option<u64> = env::var("JWT_KEY_ROTATION_INTERVAL_SECONDS")
+            .ok()
+            .map(|s| s.parse().expect("JWT_KEY_ROTATION_INTERVAL_SECONDS must be a number"));
+        match jwt_key_rotation_interval_seconds {
+            Some(0) => panic!("JWT_KEY_ROTATION_INTERVAL_SECONDS must be greater than 0"),
+            // A generated secret would not be known to anyone verifying tokens with the configured one
+            Some(_) if is_symmetric(jwt_algorithm) => {
+                panic!("JWT_KEY_ROTATION_INTERVAL_SECONDS can not be used with {:?}, a shared secret can not \
+                be rotated", jwt_algorithm)
+            }
+            _ => {}
+        }
+
+        // Secrets that signed tokens before JWT_SECRET_KEY was changed, only used for verification
+        let jwt_previous_secret_keys: Vec<String> = env::var("JWT_PREVIOUS_SECRET_KEYS")
+            .map(|secrets| secrets.split(',').filter(|secret| !secret.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        if !jwt_previous_secret_keys.is_empty() && !is_symmetric(jwt_algorithm) {
+            panic!("JWT_PREVIOUS_SECRET_KEYS can only be used with HS256, HS384 or HS512");
+        }
+
+        let ldap_srv_domain = env::var("LDAP_SRV_DOMAIN").ok();
+        let ldap_srv_ldaps = env_bool("LDAP_SRV_LDAPS", false);
+        // A comma separated list of servers, or the servers found by the SRV lookup
+        let ldap_urls: Vec<String> = match (env::var("LDAP_URL"), &ldap_srv_domain) {
+            (Ok(_), Some(_)) => panic!("Only one of LDAP_URL and LDAP_SRV_DOMAIN can be set"),
+            (Ok(urls), None) => urls
+                .split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect(),
+            (Err(_), Some(_)) => vec![],
+            (Err(_), None) => panic!("LDAP_URL or LDAP_SRV_DOMAIN must be set"),
+        };
+        if ldap_srv_domain.is_none() && ldap_urls.is_empty() {
+            panic!("LDAP_URL must contain at least one URL");
+        }
+        let ldap_schemes: Vec<&str> = match &ldap_srv_domain {
+            Some(_) if ldap_srv_ldaps => vec!["ldaps"],
+            Some(_) => vec!["ldap"],
+            None => ldap_urls.iter().map(|url| url.split("://").next().unwrap_or_default()).collect(),
+        };
+        let ldap_starttls = env_bool("LDAP_STARTTLS", false);
+        let ldap_allow_plaintext = env_bool("LDAP_ALLOW_PLAINTEXT", false);
+        if let Err(err) = check_ldap_transport(&ldap_schemes, ldap_starttls, ldap_allow_plaintext) {
+            panic!("{}", err);
+        }
+        let ldap_tls_client_cert_file = env::var("LDAP_TLS_CLIENT_CERT_FILE").ok();
+        let ldap_tls_client_key_file = env::var("LDAP_TLS_CLIENT_KEY_FILE").ok();
+        if ldap_tls_client_cert_file.is_some() != ldap_tls_client_key_file.is_some() {
+            panic!("LDAP_TLS_CLIENT_CERT_FILE and LDAP_TLS_CLIENT_KEY_FILE must be set together");
+        }
+
+        let ldap_auth_mode: LdapAuthMode = env::var("LDAP_AUTH_MODE")
+            .unwrap_or_else(|_| "direct".to_string())
+            .parse()
+            .expect("LDAP_AUTH_MODE must be one of direct or search");
+        let ldap_bind_dn = env::var("LDAP_BIND_DN").ok();
+        let ldap_bind_password = env::var("LDAP_BIND_PASSWORD").ok();
+        if ldap_bind_dn.is_some() && ldap_bind_password.is_none() {
+            panic!("LDAP_BIND_PASSWORD must be set when LDAP_BIND_DN is set");
+        }
+        let ad_base_dn = env::var("AD_BASE_DN").expect("AD_BASE_DN must be set");
+
+        let ldap_nested_groups: NestedGroups = env::var("LDAP_NESTED_GROUPS")
+            .unwrap_or_else(|_| "none".to_string())
+            .parse()
+            .expect("LDAP_NESTED_GROUPS must be one of none, in_chain or walk");
+        let ldap_group_lookup: GroupLookup = env::var("LDAP_GROUP_LOOKUP")
+            .unwrap_or_else(|_| "member_of".to_string())
+            .parse()
+            .expect("LDAP_GROUP_LOOKUP must be one of member_of or search");
+        let ldap_group_search_base = env::var("LDAP_GROUP_SEARCH_BASE").ok();
+        if ldap_nested_groups != NestedGroups::None && ldap_group_search_base.is_none() {
+            panic!("LDAP_GROUP_SEARCH_BASE must be set to resolve nested groups");
+        }
+        if ldap_group_lookup == GroupLookup::Search && ldap_group_search_base.is_none() {
+            panic!("LDAP_GROUP_SEARCH_BASE must be set to search for groups");
+        }
+
+        let ldap_pool_min_size: usize = env::var("LDAP_POOL_MIN_SIZE")
+            .map(|s| s.parse().expect("LDAP_POOL_MIN_SIZE must be a number"))
+            .unwrap_or(0);
+        let ldap_pool_max_size: usize = env::var("LDAP_POOL_MAX_SIZE")
+            .map(|s| s.parse().expect("LDAP_POOL_MAX_SIZE must be a number"))
+            .unwrap_or(10);
+        if ldap_pool_max_size == 0 || ldap_pool_min_size > ldap_pool_max_size {
+            panic!("LDAP_POOL_MAX_SIZE must be at least 1 and at least LDAP_POOL_MIN_SIZE");
+        }
+        let ldap_pool_health_check_interval_seconds: u64 = env::var("LDAP_POOL_HEALTH_CHECK_INTERVAL_SECONDS")
+            .map(|s| s.parse().expect("LDAP_POOL_HEALTH_CHECK_INTERVAL_SECONDS must be a number"))
+            .unwrap_or(30);
+        if ldap_pool_health_check_interval_seconds == 0 {
+            panic!("LDAP_POOL_HEALTH_CHECK_INTERVAL_SECONDS must be greater than 0");
+        }
+
         Config {
-            jwt_secret_key: env::var("JWT_SECRET_KEY").expect("JWT_SECRET must be set"),
+            jwt_secret_key: env::var("JWT_SECRET_KEY").ok(),
+            jwt_previous_secret_keys,
+            jwt_algorithm,
+            jwt_key_id: env::var("JWT_KEY_ID").ok(),
+            jwt_private_key_path: env::var("JWT_PRIVATE_KEY_PATH").ok(),
+            jwt_public_key_path: env::var("JWT_PUBLIC_KEY_PATH").ok(),
             jwt_expiration_time_seconds: token_expiration,
+            jwt_issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "authio".to_string()),
+            jwt_audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "authio".to_string()),
+            jwt_leeway_seconds: env::var("JWT_LEEWAY_SECONDS")
+                .map(|s| s.parse().expect("JWT_LEEWAY_SECONDS must be a number"))
+                .unwrap_or(60),
+            jwt_key_rotation_interval_seconds,
+            refresh_token_expiration_time_seconds: env::var("REFRESH_TOKEN_EXPIRATION_TIME_SECONDS")
+                .map(|s| s.parse().expect("REFRESH_TOKEN_EXPIRATION_TIME_SECONDS must be a number"))
+                .unwrap_or(86400),
             jwt_company: env::var("JWT_COMPANY").expect("JWT_COMPANY must be set"),
-            ldap_url: env::var("LDAP_URL").expect("LDAP_URL must be set"),
+            ldap_urls,
+            ldap_srv_domain,
+            ldap_srv_ldaps,
+            ldap_server_selection: env::var("LDAP_SERVER_SELECTION")
+                .unwrap_or_else(|_| "priority".to_string())
+                .parse()
+                .expect("LDAP_SERVER_SELECTION must be one of priority or round_robin"),
+            ldap_server_retry_seconds: env::var("LDAP_SERVER_RETRY_SECONDS")
+                .map(|s| s.parse().expect("LDAP_SERVER_RETRY_SECONDS must be a number"))
+                .unwrap_or(30),
+            ldap_connect_timeout_seconds: env::var("LDAP_CONNECT_TIMEOUT_SECONDS")
+                .map(|s| s.parse().expect("LDAP_CONNECT_TIMEOUT_SECONDS must be a number"))
+                .unwrap_or(5),
+            ldap_starttls,
+            ldap_tls_ca_file: env::var("LDAP_TLS_CA_FILE").ok(),
+            ldap_tls_client_cert_file,
+            ldap_tls_client_key_file,
+            ldap_tls_verify_hostname: env_bool("LDAP_TLS_VERIFY_HOSTNAME", true),
+            ldap_allow_plaintext,
+            ldap_auth_mode,
+            ldap_bind_dn,
+            ldap_bind_password,
+            ldap_user_search_base: env::var("LDAP_USER_SEARCH_BASE").unwrap_or_else(|_| ad_base_dn.clone()),
+            ldap_user_filter: env::var("LDAP_USER_FILTER").unwrap_or_else(|_| "(cn={username})".to_string()),
+            ldap_report_account_locked: env_bool("LDAP_REPORT_ACCOUNT_LOCKED", false),
+            ldap_group_lookup,
+            ldap_group_filter: env::var("LDAP_GROUP_FILTER").unwrap_or_else(|_| {
+                "(|(&(objectClass=groupOfNames)(member={dn}))\
+                (&(objectClass=groupOfUniqueNames)(uniqueMember={dn}))\
+                (&(objectClass=posixGroup)(memberUid={uid})))"
+                    .to_string()
+            }),
+            ldap_nested_groups,
+            ldap_group_search_base,
+            ldap_group_member_filter: env::var("LDAP_GROUP_MEMBER_FILTER")
+                .unwrap_or_else(|_| "(|(member={dn})(uniqueMember={dn}))".to_string()),
+            ldap_nested_groups_max_depth: env::var("LDAP_NESTED_GROUPS_MAX_DEPTH")
+                .map(|s| s.parse().expect("LDAP_NESTED_GROUPS_MAX_DEPTH must be a number"))
+                .unwrap_or(10),
+            ldap_group_descriptions: env_bool("LDAP_GROUP_DESCRIPTIONS", true),
+            ldap_group_description_attr: env::var("LDAP_GROUP_DESCRIPTION_ATTR")
+                .unwrap_or_else(|_| "description".to_string()),
+            ldap_group_mapping_file: env::var("LDAP_GROUP_MAPPING_FILE").ok(),
+            ldap_permission_name: env::var("LDAP_PERMISSION_NAME")
+                .unwrap_or_else(|_| "cn".to_string())
+                .parse()
+                .expect("LDAP_PERMISSION_NAME must be one of cn, dn or rdn:<attribute type>"),
+            ldap_permission_id: env::var("LDAP_PERMISSION_ID")
+                .ok()
+                .map(|s| s.parse().expect("LDAP_PERMISSION_ID must be one of objectGUID, objectSid or entryUUID")),
+            permission_failure_policy: env::var("PERMISSION_FAILURE_POLICY")
+                .unwrap_or_else(|_| "fail".to_string())
+                .parse()
+                .expect("PERMISSION_FAILURE_POLICY must be one of fail, degraded or proceed"),
+            ldap_pool_min_size,
+            ldap_pool_max_size,
+            ldap_pool_idle_timeout_seconds: env::var("LDAP_POOL_IDLE_TIMEOUT_SECONDS")
+                .map(|s| s.parse().expect("LDAP_POOL_IDLE_TIMEOUT_SECONDS must be a number"))
+                .unwrap_or(300),
+            ldap_pool_acquire_timeout_seconds: env::var("LDAP_POOL_ACQUIRE_TIMEOUT_SECONDS")
+                .map(|s| s.parse().expect("LDAP_POOL_ACQUIRE_TIMEOUT_SECONDS must be a number"))
+                .unwrap_or(5),
+            ldap_pool_health_check_interval_seconds,
             http_bind_address: env::var("HTTP_BIND_ADDRESS").expect("HTTP_BIND_ADDRESS must be set"),
             http_port: env::var("HTTP_PORT").expect("HTTP_PORT must be set").parse().unwrap(),
-            ad_base_dn: env::var("AD_BASE_DN").expect("AD_BASE_DN must be set"),
+            ad_base_dn,
             ad_filter_format: env::var("AD_FILTER_FORMAT").expect("AD_FILTER_FORMAT must be set"),
             ad_attrs: env::var("AD_FILTER_ATTRS")
                 .expect("AD_FILTER_ATTRS must be set")
                 .split(",")
                 .map(|s| s.to_string())
                 .collect(),
+            forward_auth_user_header: forward_auth_header("FORWARD_AUTH_USER_HEADER", "X-Auth-User"),
+            forward_auth_company_header: forward_auth_header("FORWARD_AUTH_COMPANY_HEADER", "X-Auth-Company"),
+            forward_auth_permissions_header: forward_auth_header(
+                "FORWARD_AUTH_PERMISSIONS_HEADER",
+                "X-Auth-Permissions",
+            ),
+            admin_api_key: env::var("ADMIN_API_KEY").ok(),
+            introspection_api_key: env::var("INTROSPECTION_API_KEY").ok(),
+            dummy_users_file: env::var("DUMMY_USERS_FILE").ok(),
+        }
+    }
+}
+
+/// How the LDAP connector finds the entry a user binds as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LdapAuthMode {
+    /// Bind directly as `CN={username},{AD_BASE_DN}`
+    Direct,
+    /// Bind as the service account, search for the user with `LDAP_USER_FILTER` and bind with the DN found
+    Search,
+}
+
+impl FromStr for LdapAuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "direct" => Ok(LdapAuthMode::Direct),
+            "search" => Ok(LdapAuthMode::Search),
+            _ => Err(format!("Unknown LDAP authentication mode: {}", s)),
+        }
+    }
+}
+
+/// How the LDAP server a connection is opened to is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServerSelection {
+    /// The first healthy server, in the order of `LDAP_URL` or by SRV priority and weight
+    Priority,
+    /// Each healthy server in turn, spreading the connections over all servers
+    RoundRobin,
+}
+
+impl FromStr for ServerSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "priority" => Ok(ServerSelection::Priority),
+            "round_robin" => Ok(ServerSelection::RoundRobin),
+            _ => Err(format!("Unknown server selection: {}", s)),
+        }
+    }
+}
+
+/// How the direct group memberships of a user are found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupLookup {
+    /// Read the `memberOf` attribute of the user, maintained by Active Directory and the OpenLDAP memberof overlay
+    MemberOf,
+    /// Search `LDAP_GROUP_SEARCH_BASE` for groups that list the user as member with `LDAP_GROUP_FILTER`
+    Search,
+}
+
+impl FromStr for GroupLookup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "member_of" => Ok(GroupLookup::MemberOf),
+            "search" => Ok(GroupLookup::Search),
+            _ => Err(format!("Unknown group lookup: {}", s)),
+        }
+    }
+}
+
+/// How nested group memberships are resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NestedGroups {
+    /// Only the direct `memberOf` values of the user grant permissions
+    None,
+    /// Search with `LDAP_MATCHING_RULE_IN_CHAIN`, supported by Active Directory
+    InChain,
+    /// Walk up the group hierarchy breadth-first with `LDAP_GROUP_MEMBER_FILTER`, e.g. for OpenLDAP
+    Walk,
+}
+
+impl FromStr for NestedGroups {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(NestedGroups::None),
+            "in_chain" => Ok(NestedGroups::InChain),
+            "walk" => Ok(NestedGroups::Walk),
+            _ => Err(format!("Unknown nested group resolution: {}", s)),
+        }
+    }
+}
+
+/// What the name of a permission granted by a group is derived from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum PermissionName {
+    /// The CN of the leaf RDN of the group, or the value of the leaf RDN if it has no CN
+    #[default]
+    Cn,
+    /// The full DN of the group
+    Dn,
+    /// The value of the first RDN of the given attribute type, from the leaf to the root
+    Rdn(String),
+}
+
+impl FromStr for PermissionName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cn" => Ok(PermissionName::Cn),
+            "dn" => Ok(PermissionName::Dn),
+            _ => match s.split_once(':') {
+                Some((prefix, attr_type)) if prefix.eq_ignore_ascii_case("rdn") && !attr_type.is_empty() => {
+                    Ok(PermissionName::Rdn(attr_type.to_string()))
+                }
+                _ => Err(format!("Unknown permission name: {}", s)),
+            },
+        }
+    }
+}
+
+/// The attribute of a group used as the stable identifier of the permissions it grants
+#[derive(Debug, Clone, PartialEq)]
+pub enum PermissionId {
+    /// The binary GUID of Active Directory objects, formatted as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`
+    ObjectGuid,
+    /// The binary security identifier of Active Directory objects, formatted as `S-1-5-21-...`
+    ObjectSid,
+    /// The UUID of OpenLDAP entries (RFC 4530)
+    EntryUuid,
+}
+
+impl PermissionId {
+    /// The name of the attribute
+    pub fn attribute(&self) -> &'static str {
+        match self {
+            PermissionId::ObjectGuid => "objectGUID",
+            PermissionId::ObjectSid => "objectSid",
+            PermissionId::EntryUuid => "entryUUID",
+        }
+    }
+}
+
+impl FromStr for PermissionId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "objectguid" => Ok(PermissionId::ObjectGuid),
+            "objectsid" => Ok(PermissionId::ObjectSid),
+            "entryuuid" => Ok(PermissionId::EntryUuid),
+            _ => Err(format!("Unknown permission id: {}", s)),
         }
     }
 }
 
+/// What happens when the permissions of an authenticated user can not be resolved completely
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermissionFailurePolicy {
+    /// Refuse the token, the client can try again later
+    Fail,
+    /// Issue a token with the permissions that were found, marked as `degraded`
+    Degraded,
+    /// Issue a token with the permissions that were found, as if they were complete
+    Proceed,
+}
+
+impl FromStr for PermissionFailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fail" => Ok(PermissionFailurePolicy::Fail),
+            "degraded" => Ok(PermissionFailurePolicy::Degraded),
+            "proceed" => Ok(PermissionFailurePolicy::Proceed),
+            _ => Err(format!("Unknown permission failure policy: {}", s)),
+        }
+    }
+}
+
+/// Check that the LDAP servers are reached in a way that does not send passwords in clear text
+///
+/// # Arguments
+/// * `schemes` - The URL schemes of the LDAP servers, e.g. `ldaps`
+/// * `starttls` - `LDAP_STARTTLS`
+/// * `allow_plaintext` - `LDAP_ALLOW_PLAINTEXT`
+/// # Returns
+/// * Why the combination is refused, if it is
+pub fn check_ldap_transport(schemes: &[&str], starttls: bool, allow_plaintext: bool) -> Result<(), String> {
+    for scheme in schemes {
+        let tls = scheme.eq_ignore_ascii_case("ldaps");
+        // ldapi:// is a local socket, passwords never cross the network
+        let local = scheme.eq_ignore_ascii_case("ldapi");
+        if tls && starttls {
+            return Err("LDAP_STARTTLS can not be used with an ldaps:// LDAP_URL".to_string());
+        }
+        if !tls && !starttls && !local && !allow_plaintext {
+            return Err("LDAP_URL does not use TLS, passwords would be sent in clear text. Use an ldaps:// URL, \
+            set LDAP_STARTTLS=true, or set LDAP_ALLOW_PLAINTEXT=true to allow it anyway"
+                .to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Read a boolean variable, `true` or `false`.
+fn env_bool(variable: &str, default: bool) -> bool {
+    match env::var(variable) {
+        Ok(value) => value
+            .to_lowercase()
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be true or false", variable)),
+        Err(_) => default,
+    }
+}
+
+/// Read the name of a forward auth response header. Setting the variable to an empty string disables the header.
+fn forward_auth_header(variable: &str, default: &str) -> Option<String> {
+    let header = env::var(variable).unwrap_or_else(|_| default.to_string());
+    (!header.is_empty()).then_some(header)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Use `lazy_static` to initialize the configuration once and make it globally available.
 // This is a good solution for configuration that is read-only and should be available everywhere.
 lazy_static! {
@@ -1,11 +1,11 @@
-use std::pin::Pin;
 use serde::{Deserialize, Serialize};
-use crate::connectors::ldap::LdapConnector;
-use crate::traits::auth::Auth;
 
 /// Enum for available connectors
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Defaults to `Ldap`, so requests without a connector keep authenticating against the LDAP server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Connector {
+    #[default]
     Ldap,
     Dummy,
 }
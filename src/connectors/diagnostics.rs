@@ -0,0 +1,192 @@
+use crate::models::AuthFailure;
+use ldap3::controls::{Control, RawControl};
+use ldap3::{LdapResult, SearchEntry};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// OID of the password policy control (draft-behera-ldap-password-policy), used by the OpenLDAP ppolicy overlay
+pub const PASSWORD_POLICY_OID: &str = "1.3.6.1.4.1.42.2.27.8.5.1";
+
+/// The attributes of a user entry that tell whether the account can still be used: the Active Directory account
+/// flags and the lock of the OpenLDAP ppolicy overlay, which is an operational attribute and must be asked for
+pub const ACCOUNT_STATUS_ATTRS: [&str; 2] = ["userAccountControl", "pwdAccountLockedTime"];
+
+/// The `userAccountControl` flag of a disabled Active Directory account
+const ACCOUNTDISABLE: u32 = 0x2;
+
+/// The LDAP result code of a failed bind (RFC 4511)
+const INVALID_CREDENTIALS: u32 = 49;
+/// The LDAP result codes of a server that is too busy or unable to answer (RFC 4511)
+const BUSY: u32 = 51;
+const UNAVAILABLE: u32 = 52;
+
+lazy_static! {
+    /// The sub-code Active Directory puts into the diagnostic message of a failed bind, e.g.
+    /// `80090308: LdapErr: DSID-0C09044E, comment: AcceptSecurityContext error, data 52e, v4563`
+    static ref AD_DATA: Regex = Regex::new(r"(?i)\bdata ([0-9a-f]+)\b").unwrap();
+}
+
+/// The request control asking the server for the password policy state of the account on bind
+///
+/// The control is not critical, servers that do not support it ignore it.
+pub fn password_policy_request() -> RawControl {
+    RawControl {
+        ctype: PASSWORD_POLICY_OID.to_string(),
+        crit: false,
+        val: None,
+    }
+}
+
+/// Returns `true` if the server could not process the bind, so its result says nothing about the credentials
+pub fn server_unavailable(result: &LdapResult) -> bool {
+    result.rc == BUSY || result.rc == UNAVAILABLE
+}
+
+/// Find out why a bind failed, or that it must not be accepted despite succeeding
+///
+/// The password policy response control is checked first, then the Active Directory sub-code of the
+/// diagnostic message.
+///
+/// # Arguments
+/// * `result` - The result of the bind
+/// # Returns
+/// * None if the bind succeeded and the account can be used, otherwise the reason it can not
+pub fn bind_failure(result: &LdapResult) -> Option<AuthFailure> {
+    let policy_error = result.ctrls.iter().find_map(|Control(_, raw)| match raw.ctype == PASSWORD_POLICY_OID {
+        true => raw.val.as_deref().and_then(password_policy_error),
+        false => None,
+    });
+
+    if result.rc == 0 {
+        // The ppolicy overlay accepts the bind, but restricts the session until the password is changed
+        return match policy_error {
+            Some(AuthFailure::PasswordMustChange) => Some(AuthFailure::PasswordMustChange),
+            _ => None,
+        };
+    }
+
+    if result.rc == INVALID_CREDENTIALS {
+        if let Some(failure) = policy_error {
+            return Some(failure);
+        }
+        if let Some(failure) = AD_DATA.captures(&result.text).and_then(|captures| ad_failure(&captures[1])) {
+            return Some(failure);
+        }
+    }
+    Some(AuthFailure::InvalidCredentials)
+}
+
+/// Find out whether the account of a user entry can no longer be used, without a bind
+///
+/// Used when tokens are refreshed, since the user does not log in again. The entry must have been read with
+/// `ACCOUNT_STATUS_ATTRS`.
+///
+/// # Returns
+/// * `AccountDisabled` if the Active Directory account is disabled, `AccountLocked` if the ppolicy overlay locked
+///   it, otherwise None
+pub fn account_failure(entry: &SearchEntry) -> Option<AuthFailure> {
+    let attribute = |name: &str| {
+        entry
+            .attrs
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .and_then(|(_, values)| values.first())
+    };
+
+    let flags = attribute("userAccountControl").and_then(|flags| flags.parse::<u32>().ok());
+    if flags.is_some_and(|flags| flags & ACCOUNTDISABLE != 0) {
+        return Some(AuthFailure::AccountDisabled);
+    }
+    if attribute("pwdAccountLockedTime").is_some() {
+        return Some(AuthFailure::AccountLocked);
+    }
+    None
+}
+
+/// Report a locked account like a wrong password, unless `report_account_locked` is set
+///
+/// Active Directory (775) and the ppolicy overlay report a lock without checking the password, so reporting it
+/// would let anyone find out whether an account exists and is locked.
+pub fn hide_account_locked(failure: AuthFailure, report_account_locked: bool) -> AuthFailure {
+    match failure {
+        AuthFailure::AccountLocked if !report_account_locked => AuthFailure::InvalidCredentials,
+        failure => failure,
+    }
+}
+
+/// Map an Active Directory bind sub-code to the reason the bind failed
+///
+/// Active Directory reports most sub-codes only when the password is right.
+pub fn ad_failure(sub_code: &str) -> Option<AuthFailure> {
+    match sub_code.to_lowercase().as_str() {
+        // 525: user not found, reported like a wrong password so usernames can not be probed
+        "52e" | "525" => Some(AuthFailure::InvalidCredentials),
+        "530" | "531" => Some(AuthFailure::LogonRestricted),
+        "532" => Some(AuthFailure::PasswordExpired),
+        "533" => Some(AuthFailure::AccountDisabled),
+        "701" => Some(AuthFailure::AccountExpired),
+        "773" => Some(AuthFailure::PasswordMustChange),
+        "775" => Some(AuthFailure::AccountLocked),
+        _ => None,
+    }
+}
+
+/// Read the error of a password policy response control
+///
+/// ```text
+/// PasswordPolicyResponseValue ::= SEQUENCE {
+///     warning [0] CHOICE { timeBeforeExpiration [0] INTEGER, graceAuthNsRemaining [1] INTEGER } OPTIONAL,
+///     error   [1] ENUMERATED { passwordExpired (0), accountLocked (1), changeAfterReset (2), ... } OPTIONAL }
+/// ```
+///
+/// # Returns
+/// * The reason the bind failed, or None if the control has no error that prevents a login or is malformed
+pub fn password_policy_error(value: &[u8]) -> Option<AuthFailure> {
+    let (tag, mut content, _) = ber_element(value)?;
+    if tag != 0x30 {
+        return None;
+    }
+
+    while !content.is_empty() {
+        let (tag, element, rest) = ber_element(content)?;
+        // [1] error, context specific and primitive
+        if tag == 0x81 {
+            return match element {
+                [0] => Some(AuthFailure::PasswordExpired),
+                [1] => Some(AuthFailure::AccountLocked),
+                [2] => Some(AuthFailure::PasswordMustChange),
+                _ => None,
+            };
+        }
+        content = rest;
+    }
+    None
+}
+
+/// Split the first BER element off the input
+///
+/// # Returns
+/// * The tag, the content and the rest of the input, or None if the element is truncated
+fn ber_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+
+    let length = match first {
+        length if length < 0x80 => length as usize,
+        long_form => {
+            let octets = (long_form & 0x7f) as usize;
+            if octets == 0 || octets > 4 || input.len() < octets {
+                return None;
+            }
+            let length = input[..octets].iter().fold(0usize, |length, byte| length << 8 | *byte as usize);
+            input = &input[octets..];
+            length
+        }
+    };
+
+    if input.len() < length {
+        return None;
+    }
+    let (content, rest) = input.split_at(length);
+    Some((tag, content, rest))
+}
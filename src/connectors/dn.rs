@@ -0,0 +1,275 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A distinguished name, parsed according to RFC 4514
+///
+/// The RDNs are ordered from the leaf to the root, e.g. `CN=tool1` comes first in
+/// `CN=tool1,OU=tools,DC=example,DC=com`. Escaped characters are unescaped, so the value of the leaf RDN of
+/// `CN=Smith\, John,OU=people` is `Smith, John`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dn {
+    pub rdns: Vec<Rdn>,
+}
+
+/// A relative distinguished name, one or more attributes joined by `+`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rdn {
+    pub attributes: Vec<Attribute>,
+}
+
+/// An attribute type and value of an RDN
+///
+/// Values in the `#` hex form are kept as they are, as their BER encoding is not decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub attr_type: String,
+    pub value: String,
+}
+
+impl Dn {
+    /// The leaf RDN, None for the empty DN
+    pub fn leaf(&self) -> Option<&Rdn> {
+        self.rdns.first()
+    }
+
+    /// The value of the first attribute of the given type, searching from the leaf to the root
+    ///
+    /// Attribute types are compared case-insensitively.
+    pub fn value(&self, attr_type: &str) -> Option<&str> {
+        self.rdns.iter().find_map(|rdn| rdn.value(attr_type))
+    }
+
+    /// Returns `true` if both DNs name the same entry
+    ///
+    /// Attribute types and values are compared case-insensitively, and the attributes of a multi-valued RDN in
+    /// any order. Formatting differences such as spaces around separators or escaping are already gone after
+    /// parsing.
+    pub fn matches(&self, other: &Dn) -> bool {
+        self.rdns.len() == other.rdns.len() && self.rdns.iter().zip(&other.rdns).all(|(a, b)| a.matches(b))
+    }
+}
+
+impl Rdn {
+    /// The value of the attribute of the given type, compared case-insensitively
+    pub fn value(&self, attr_type: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.attr_type.eq_ignore_ascii_case(attr_type))
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    /// Returns `true` if both RDNs have the same attributes, in any order
+    fn matches(&self, other: &Rdn) -> bool {
+        let contains = |rdn: &Rdn, attribute: &Attribute| {
+            rdn.attributes.iter().any(|candidate| {
+                candidate.attr_type.eq_ignore_ascii_case(&attribute.attr_type)
+                    && candidate.value.to_lowercase() == attribute.value.to_lowercase()
+            })
+        };
+        self.attributes.len() == other.attributes.len()
+            && self.attributes.iter().all(|attribute| contains(other, attribute))
+    }
+}
+
+impl FromStr for Dn {
+    type Err = String;
+
+    /// Parse a DN
+    ///
+    /// Spaces around the separators are ignored, as many directories still produce RFC 2253 style DNs with them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser { input: s.as_bytes(), position: 0 }.dn()
+    }
+}
+
+impl fmt::Display for Dn {
+    /// Format the DN with the special characters of the values escaped again
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rdns: Vec<String> = self
+            .rdns
+            .iter()
+            .map(|rdn| {
+                let attributes: Vec<String> = rdn
+                    .attributes
+                    .iter()
+                    .map(|attribute| {
+                        let value = if attribute.value.starts_with('#') {
+                            attribute.value.clone()
+                        } else {
+                            ldap3::dn_escape(attribute.value.as_str()).into_owned()
+                        };
+                        format!("{}={}", attribute.attr_type, value)
+                    })
+                    .collect();
+                attributes.join("+")
+            })
+            .collect();
+        write!(f, "{}", rdns.join(","))
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn dn(&mut self) -> Result<Dn, String> {
+        let mut rdns = vec![];
+
+        self.skip_spaces();
+        if self.at_end() {
+            return Ok(Dn { rdns });
+        }
+
+        loop {
+            rdns.push(self.rdn()?);
+            match self.next() {
+                None => return Ok(Dn { rdns }),
+                Some(b',') => continue,
+                Some(c) => return Err(self.error(&format!("Unexpected '{}'", c as char))),
+            }
+        }
+    }
+
+    fn rdn(&mut self) -> Result<Rdn, String> {
+        let mut attributes = vec![self.attribute()?];
+        while self.peek() == Some(b'+') {
+            self.position += 1;
+            attributes.push(self.attribute()?);
+        }
+        Ok(Rdn { attributes })
+    }
+
+    fn attribute(&mut self) -> Result<Attribute, String> {
+        self.skip_spaces();
+        let attr_type = self.attr_type()?;
+        self.skip_spaces();
+        if self.next() != Some(b'=') {
+            return Err(self.error(&format!("Expected '=' after attribute type {}", attr_type)));
+        }
+        self.skip_spaces();
+        let value = self.value()?;
+        Ok(Attribute { attr_type, value })
+    }
+
+    /// A descriptor such as `CN`, or a numeric OID such as `2.5.4.3`
+    fn attr_type(&mut self) -> Result<String, String> {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == b'-' || c == b'.' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let attr_type = &self.input[start..self.position];
+        let valid = match attr_type.first() {
+            Some(c) if c.is_ascii_alphabetic() => attr_type.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'-'),
+            Some(c) if c.is_ascii_digit() => {
+                attr_type.split(|c| *c == b'.').all(|part| !part.is_empty() && part.iter().all(u8::is_ascii_digit))
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(self.error("Invalid attribute type"));
+        }
+        Ok(String::from_utf8_lossy(attr_type).into_owned())
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        if self.peek() == Some(b'#') {
+            return self.hex_value();
+        }
+
+        let mut value: Vec<u8> = vec![];
+        // Length of the value up to the last character that is not an unescaped trailing space
+        let mut significant = 0;
+
+        while let Some(c) = self.peek() {
+            match c {
+                b',' | b'+' => break,
+                b'\\' => {
+                    self.position += 1;
+                    value.push(self.escaped()?);
+                    significant = value.len();
+                }
+                b'"' | b';' | b'<' | b'>' | 0 => {
+                    return Err(self.error(&format!("Character '{}' must be escaped", c.escape_ascii())));
+                }
+                _ => {
+                    self.position += 1;
+                    value.push(c);
+                    if c != b' ' {
+                        significant = value.len();
+                    }
+                }
+            }
+        }
+
+        value.truncate(significant);
+        String::from_utf8(value).map_err(|_| self.error("Value is not valid UTF-8"))
+    }
+
+    /// The character after a backslash, either a special character or two hex digits
+    fn escaped(&mut self) -> Result<u8, String> {
+        match self.next() {
+            Some(c) if b" \"#+,;<=>\\".contains(&c) => Ok(c),
+            Some(high) if high.is_ascii_hexdigit() => match self.next() {
+                Some(low) if low.is_ascii_hexdigit() => Ok(hex(high) << 4 | hex(low)),
+                _ => Err(self.error("Invalid hex escape")),
+            },
+            _ => Err(self.error("Invalid escape")),
+        }
+    }
+
+    fn hex_value(&mut self) -> Result<String, String> {
+        let start = self.position;
+        self.position += 1;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.position += 1;
+        }
+
+        let digits = self.position - start - 1;
+        if digits == 0 || digits % 2 == 1 {
+            return Err(self.error("Invalid hex string value"));
+        }
+        self.skip_spaces();
+        Ok(String::from_utf8_lossy(&self.input[start..start + 1 + digits]).into_owned())
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at position {}", message, self.position)
+    }
+}
+
+fn hex(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
+}
@@ -0,0 +1,148 @@
+use crate::models::{AuthError, AuthFailure, AuthOutcome, Permission, Principal, ResolvedPermissions};
+use crate::traits::auth::Auth;
+use crate::traits::authenticate::Authenticate;
+use crate::traits::authorize::Authorize;
+use openssl::memcmp;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// A user of the dummy connector
+///
+/// ### Arguments
+/// * `username` - The name the user logs in with
+/// * `password` - The password of the user, in plain text
+/// * `permissions` - The permissions granted to the user
+#[derive(Deserialize)]
+struct DummyUser {
+    username: String,
+    password: String,
+    #[serde(default)]
+    permissions: Vec<Permission>,
+}
+
+/// Static set of users backing the dummy connector
+///
+/// The users are read from a JSON file once at startup:
+///
+/// ```json
+/// {
+///   "users": [
+///     {
+///       "username": "tester",
+///       "password": "password",
+///       "permissions": [{ "name": "tool1", "description": "Tool 1", "access_type": "WRITE" }]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Passwords are stored in plain text, the dummy connector is meant for local development and load
+/// testing only.
+pub struct DummyDirectory {
+    users: HashMap<String, DummyUser>,
+}
+
+#[derive(Deserialize)]
+struct DummyUsersFile {
+    users: Vec<DummyUser>,
+}
+
+impl DummyDirectory {
+    /// Parse a users file
+    ///
+    /// # Arguments
+    /// * `json` - The content of the users file
+    /// # Returns
+    /// * The directory, or a description of why the file is invalid
+    pub fn parse(json: &str) -> Result<DummyDirectory, String> {
+        let file: DummyUsersFile = serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        let mut users = HashMap::new();
+        for user in file.users {
+            if users.contains_key(&user.username) {
+                return Err(format!("User {} is defined more than once", user.username));
+            }
+            users.insert(user.username.clone(), user);
+        }
+        Ok(DummyDirectory { users })
+    }
+
+    /// Read and parse a users file
+    ///
+    /// # Arguments
+    /// * `path` - The path of the users file
+    pub fn load(path: &str) -> Result<DummyDirectory, String> {
+        let json = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+        DummyDirectory::parse(&json).map_err(|err| format!("Invalid users file {}: {}", path, err))
+    }
+}
+
+/// Connector that authenticates against a static `DummyDirectory` instead of an LDAP server
+pub struct DummyConnector {
+    directory: Arc<DummyDirectory>,
+}
+
+impl DummyConnector {
+    pub fn new(directory: Arc<DummyDirectory>) -> DummyConnector {
+        DummyConnector { directory }
+    }
+}
+
+impl Auth for DummyConnector {}
+
+impl Authenticate for DummyConnector {
+    /// Authenticate a user against the users file.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user to authenticate.
+    /// * `password` - The password of the user to authenticate.
+    /// # Returns
+    /// * The user if they exist and the password matches, invalid credentials otherwise.
+    fn authenticate<'a>(
+        &'a mut self,
+        username: &'a str,
+        password: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<AuthOutcome, AuthError>> + Send + 'a>> {
+        Box::pin(async move {
+            let authenticated = match self.directory.users.get(username) {
+                Some(user) => {
+                    user.password.len() == password.len()
+                        && memcmp::eq(user.password.as_bytes(), password.as_bytes())
+                }
+                None => {
+                    log::debug!("Unknown user: {}", username);
+                    false
+                }
+            };
+
+            Ok(match authenticated {
+                true => AuthOutcome::Authenticated(Principal::new(username)),
+                false => AuthOutcome::Denied(AuthFailure::InvalidCredentials),
+            })
+        })
+    }
+}
+
+impl Authorize for DummyConnector {
+    /// Resolve the permissions of a user from the users file.
+    ///
+    /// # Arguments
+    /// * `identifier` - The username of the user to resolve permissions for.
+    /// # Returns
+    /// * The permissions of the user, `AuthError::UnknownUser` if the user does not exist.
+    fn resolve_permission<'a>(
+        &'a mut self,
+        identifier: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ResolvedPermissions, AuthError>> + Send + 'a>> {
+        Box::pin(async move {
+            match self.directory.users.get(identifier) {
+                Some(user) => Ok(ResolvedPermissions::complete(user.permissions.clone())),
+                None => Err(AuthError::UnknownUser(identifier.to_string())),
+            }
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use ldap3::{ldap_escape, SearchEntry};
+use std::collections::HashSet;
+
+/// OID of `LDAP_MATCHING_RULE_IN_CHAIN`, which makes Active Directory follow nested group memberships
+pub const MATCHING_RULE_IN_CHAIN: &str = "1.2.840.113556.1.4.1941";
+
+/// The direct group memberships, the `memberOf` values, of the entries
+pub fn member_of(entries: &[SearchEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.attrs.get("memberOf"))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// A group the user is a member of
+///
+/// ### Arguments
+/// * `dn` - The DN of the group
+/// * `description` - The description of the group, if it has one and it has been looked up
+/// * `id` - The stable identifier of the group, if `LDAP_PERMISSION_ID` is set and it has been looked up
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub dn: String,
+    pub description: Option<String>,
+    pub id: Option<String>,
+}
+
+/// The first value of an attribute of an entry, the attribute type is compared case-insensitively
+pub fn attribute<'a>(entry: &'a SearchEntry, attr_type: &str) -> Option<&'a str> {
+    entry
+        .attrs
+        .iter()
+        .find(|(attr, _)| attr.eq_ignore_ascii_case(attr_type))
+        .and_then(|(_, values)| values.first())
+        .map(String::as_str)
+}
+
+/// The `uid` of an entry, the value posix groups list their members by
+pub fn uid(entry: &SearchEntry) -> Option<&str> {
+    attribute(entry, "uid")
+}
+
+/// Filter matching every group that lists a user as direct member
+///
+/// # Arguments
+/// * `template` - The filter, in which `{dn}` is replaced by the DN and `{uid}` by the uid of the user
+/// * `dn` - The DN of the user
+/// * `uid` - The uid of the user
+pub fn group_filter(template: &str, dn: &str, uid: &str) -> String {
+    template
+        .replace("{dn}", &ldap_escape(dn))
+        .replace("{uid}", &ldap_escape(uid))
+}
+
+/// Filter matching every group the entry is a member of, directly or through nested groups.
+/// Only supported by Active Directory.
+pub fn in_chain_filter(dn: &str) -> String {
+    format!("(member:{}:={})", MATCHING_RULE_IN_CHAIN, ldap_escape(dn))
+}
+
+/// Filter matching every group that has one of the entries as direct member
+///
+/// # Arguments
+/// * `template` - Filter matching the groups of a single entry, in which `{dn}` is replaced by its DN
+/// * `dns` - The DNs of the entries
+pub fn member_filter(template: &str, dns: &[String]) -> String {
+    let filters: Vec<String> = dns
+        .iter()
+        .map(|dn| template.replace("{dn}", &ldap_escape(dn)))
+        .collect();
+
+    match filters.len() {
+        1 => filters[0].clone(),
+        _ => format!("(|{})", filters.concat()),
+    }
+}
+
+/// Merge lists of group DNs, dropping duplicates
+///
+/// DNs are compared case-insensitively, the first spelling is kept.
+pub fn merge(groups: Vec<String>, more: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    groups
+        .into_iter()
+        .chain(more)
+        .filter(|group| seen.insert(group.to_lowercase()))
+        .collect()
+}
+
+/// Breadth-first walk up the group hierarchy, for directories without `LDAP_MATCHING_RULE_IN_CHAIN`
+///
+/// Every round, the groups of the current frontier are looked up with `member_filter` and passed to `visit`.
+/// Groups that have been seen before are not visited again, so membership cycles end the walk, and the walk
+/// stops after `max_depth` rounds.
+pub struct GroupWalk {
+    groups: Vec<String>,
+    seen: HashSet<String>,
+    frontier: Vec<String>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl GroupWalk {
+    /// Start a walk
+    ///
+    /// # Arguments
+    /// * `dn` - The DN of the user
+    /// * `direct_groups` - The groups the user is known to be a direct member of, e.g. from `memberOf`
+    /// * `max_depth` - The maximum number of rounds
+    pub fn new(dn: &str, direct_groups: Vec<String>, max_depth: usize) -> GroupWalk {
+        let mut seen = HashSet::from([dn.to_lowercase()]);
+        let groups = merge(direct_groups, vec![]);
+        seen.extend(groups.iter().map(|group| group.to_lowercase()));
+
+        let mut frontier = vec![dn.to_string()];
+        frontier.extend(groups.iter().cloned());
+
+        GroupWalk { groups, seen, frontier, depth: 0, max_depth }
+    }
+
+    /// The entries whose groups are looked up next, None when the walk is done
+    pub fn frontier(&self) -> Option<&[String]> {
+        if self.frontier.is_empty() || self.depth >= self.max_depth {
+            return None;
+        }
+        Some(&self.frontier)
+    }
+
+    /// Record the groups found for the current frontier
+    pub fn visit(&mut self, found: Vec<String>) {
+        self.depth += 1;
+        self.frontier = found
+            .into_iter()
+            .filter(|group| self.seen.insert(group.to_lowercase()))
+            .collect();
+        self.groups.extend(self.frontier.iter().cloned());
+
+        if !self.frontier.is_empty() && self.depth >= self.max_depth {
+            log::warn!("Stopped resolving nested groups after {} levels", self.max_depth);
+        }
+    }
+
+    /// Every group found, direct memberships first
+    pub fn into_groups(self) -> Vec<String> {
+        self.groups
+    }
+}
@@ -1,39 +1,86 @@
-use crate::config::CONFIG;
-use crate::models::Access;
-use crate::models::Permission;
+use crate::config::{GroupLookup, LdapAuthMode, NestedGroups, CONFIG};
+use crate::connectors::diagnostics::{
+    account_failure, bind_failure, hide_account_locked, password_policy_request, server_unavailable,
+    ACCOUNT_STATUS_ATTRS,
+};
+use crate::connectors::groups::{self, Group, GroupWalk};
+use crate::connectors::mapping::GroupMapping;
+use crate::connectors::object_id;
+use crate::connectors::pool::{bind_service_identity, LdapPool, PooledConnection};
+use crate::connectors::username::validate_username;
+use crate::models::{AuthError, AuthFailure, AuthOutcome, Principal, ResolvedPermissions};
 use crate::traits::auth::Auth;
 use crate::traits::authenticate::Authenticate;
 use crate::traits::authorize::Authorize;
-use ldap3::{drive, Ldap, LdapConnAsync, LdapError, Scope, SearchEntry, SearchResult};
+use futures::future::join_all;
+use ldap3::{dn_escape, ldap_escape, Ldap, LdapError, Scope, SearchEntry, SearchResult};
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
-impl Auth for LdapConnector {}
+impl Auth for LdapConnector {
+    fn connect(&mut self) -> Pin<Box<dyn Future<Output = Result<(), AuthError>> + Send + '_>> {
+        Box::pin(self.initialize())
+    }
+
+    fn disconnect(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.release_connection())
+    }
+}
 
 impl Authorize for LdapConnector {
     /// Resolve the permissions for a user.
     ///
-    /// If an error occurs during the permission lookup, an empty vector is returned.
+    /// The groups the user is a member of are mapped to permissions by the `GroupMapping`. The groups are
+    /// read from `memberOf`, or searched for with `LDAP_GROUP_LOOKUP=search`. With `LDAP_NESTED_GROUPS` this
+    /// includes the groups the user is a member of through other groups. The permissions are described by the
+    /// `description` of the group entries, and identified by `LDAP_PERMISSION_ID` if it is set.
+    ///
+    /// If the user can not be looked up, an `AuthError` is returned, `AuthError::UnknownUser` if the user does not
+    /// exist or does not match `AD_FILTER_FORMAT`, and `AuthError::Denied` if the account is disabled or locked. If a
+    /// group search fails, or a group entry can not be read, the permissions of the groups found so far are returned
+    /// as partial.
     ///
     /// # Arguments
     /// * `identifier` - The identifier of the user to resolve permissions for.
     /// # Returns
-    /// * A vector of `Permission` objects for the user.
+    /// * The permissions of the user, partial if a group search or read failed.
     fn resolve_permission<'a>(
         &'a mut self,
         identifier: &'a str,
-    ) -> Pin<Box<dyn Future<Output = Vec<Permission>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<ResolvedPermissions, AuthError>> + Send + 'a>> {
         Box::pin(async move {
-            let permissions: Vec<Permission> = vec![];
+            self.group_search_failure = None;
+
             // Lookup the permissions for the user
-            let search_result = self.permission_lookup(identifier).await;
+            let search_result = self.permission_lookup(identifier).await?;
 
-            if search_result.len() == 0 {
-                log::info!("No permissions found for user: {}", identifier);
-                permissions
-            } else {
-                Self::parse_search_entry(search_result)
+            if search_result.is_empty() {
+                log::info!("User not found: {}", identifier);
+                return Err(AuthError::UnknownUser(identifier.to_string()));
+            }
+            if let Some(failure) = account_failure(&search_result[0]) {
+                log::info!("Account of {} can not be used: {:?}", identifier, failure);
+                return Err(AuthError::Denied(failure));
+            }
+            let user_dn = search_result[0].dn.clone();
+            let mut direct_groups = groups::member_of(&search_result);
+            if CONFIG.ldap_group_lookup == GroupLookup::Search {
+                // Posix groups list members by uid, which is usually but not necessarily the login name
+                let uid = groups::uid(&search_result[0]).unwrap_or(identifier).to_string();
+                let filter = groups::group_filter(&CONFIG.ldap_group_filter, &user_dn, &uid);
+                let found = self.search_groups(&filter).await;
+                direct_groups = groups::merge(direct_groups, found);
             }
+            let groups = self.nested_groups(&user_dn, direct_groups).await;
+            let groups = self.read_groups(groups).await;
+            let permissions = self.mapping.permissions(&groups);
+
+            Ok(match self.group_search_failure.take() {
+                Some(failure) => ResolvedPermissions::partial(permissions, &failure),
+                None => ResolvedPermissions::complete(permissions),
+            })
         })
     }
 }
@@ -41,138 +88,295 @@ impl Authorize for LdapConnector {
 impl Authenticate for LdapConnector {
     /// Authenticate a user against the LDAP server.
     ///
+    /// The bind asks for the password policy state of the account. If the bind fails, or the account must
+    /// not be used, the reason is read from the password policy control or the Active Directory diagnostic
+    /// message.
+    ///
     /// # Arguments
     /// * `username` - The username of the user to authenticate.
     /// * `password` - The password of the user to authenticate.
     /// # Returns
-    /// * `true` if the user is authenticated, `false` otherwise.
+    /// * The user with their DN as `dn` attribute if the bind succeeded, otherwise why it failed. An
+    ///   `AuthError` if the user could not be looked up or the server did not answer the bind.
     fn authenticate<'a>(
         &'a mut self,
         username: &'a str,
         password: &'a str,
-    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<AuthOutcome, AuthError>> + Send + 'a>> {
         Box::pin(async move {
-            // The bind_dn is the user's username with the AD_FORMAT appended
-            // Example: CN=jsmith,OU=Users,OU=Accounts,DC=example,DC=com
-            let bind_dn: String = format!("CN={},{}", username, CONFIG.ad_base_dn);
+            // An empty password would make this an unauthenticated bind, which most servers accept
+            if password.is_empty() {
+                log::debug!("Bind refused: Empty password");
+                return Ok(AuthOutcome::Denied(AuthFailure::InvalidCredentials));
+            }
 
-            let ldap = match self.ldap.as_mut() {
-                Some(ldap) => ldap,
-                None => {
-                    log::error!("LDAP connection not initialized");
-                    return false;
-                }
+            // Example: CN=jsmith,OU=Users,OU=Accounts,DC=example,DC=com
+            let bind_dn: String = match self.find_user_dn(username).await? {
+                Some(bind_dn) => bind_dn,
+                None => return Ok(AuthOutcome::Denied(AuthFailure::InvalidCredentials)),
             };
 
-            let is_authenticated: bool = match ldap.simple_bind(&bind_dn, password).await {
-                Ok(res) => {
-                    if res.success().is_ok() {
+            let ldap = self.connection()?;
+            let bind = ldap.with_controls(password_policy_request()).simple_bind(&bind_dn, password);
+            match bind.await {
+                Ok(res) if server_unavailable(&res) => {
+                    log::error!("Bind failed: {}", res);
+                    Err(AuthError::BackendUnavailable(res.to_string()))
+                }
+                Ok(res) => match bind_failure(&res) {
+                    None => {
                         log::debug!("Bind successful: Authenticated");
-                        true
-                    } else {
-                        log::debug!("Bind failed: Invalid credentials");
-                        false
+                        let principal = Principal::new(username).with_attribute("dn", &bind_dn);
+                        Ok(AuthOutcome::Authenticated(principal))
                     }
-                }
+                    Some(failure) => {
+                        log::debug!("Bind failed: {:?} ({})", failure, res);
+                        Ok(AuthOutcome::Denied(hide_account_locked(failure, CONFIG.ldap_report_account_locked)))
+                    }
+                },
                 Err(err) => {
                     log::error!("Bind failed: {}", err);
-                    false
+                    Err(AuthError::BackendUnavailable(err.to_string()))
                 }
-            };
-
-            return is_authenticated;
+            }
         })
     }
 }
 
 pub struct LdapConnector {
-    ldap: Option<Ldap>,
+    pool: Arc<LdapPool>,
+    mapping: Arc<GroupMapping>,
+    ldap: Option<PooledConnection>,
+    /// The last user found by `find_user_dn` and their DN, so the search is not repeated after `authenticate`
+    user_dn: Option<(String, String)>,
+    /// The groups read by `search_groups` and `read_groups`, by lowercase DN
+    group_entries: HashMap<String, Group>,
+    /// Why a group search of the current `resolve_permission` failed, if one did
+    group_search_failure: Option<String>,
+}
+
+/// The LDAP result code of a search whose base does not exist (RFC 4511)
+const NO_SUCH_OBJECT: u32 = 32;
+
+/// The number of group entries read at the same time by `read_groups`
+const GROUP_BATCH_SIZE: usize = 50;
+
+/// The attributes of group entries that are part of their permissions
+fn group_attrs() -> Vec<&'static str> {
+    let mut attrs = vec![];
+    if CONFIG.ldap_group_descriptions {
+        attrs.push(CONFIG.ldap_group_description_attr.as_str());
+    }
+    if let Some(kind) = &CONFIG.ldap_permission_id {
+        attrs.push(kind.attribute());
+    }
+    attrs
 }
 
 impl LdapConnector {
-    pub fn new() -> LdapConnector {
-        Self { ldap: None }
+    pub fn new(pool: Arc<LdapPool>, mapping: Arc<GroupMapping>) -> LdapConnector {
+        Self {
+            pool,
+            mapping,
+            ldap: None,
+            user_dn: None,
+            group_entries: HashMap::new(),
+            group_search_failure: None,
+        }
     }
 
-    /// Extract the permissions from the search results
+    /// Resolve the groups a user is a member of, directly or through other groups.
     ///
-    /// Map the search results to a vector of Permission objects
-    /// Default the access_type to READ
-    pub fn parse_search_entry(entries: Vec<SearchEntry>) -> Vec<Permission> {
-        let mut permissions: Vec<Permission> = vec![];
-
-        for entry in entries {
-            log::debug!("DN: {}", entry.dn);
-
-            if let Some(member_of) = entry.attrs.get("memberOf") {
-                for group in member_of {
-                    let group_name: Vec<&str> = group.split(",").collect();
-                    let group_name: Vec<&str> = group_name[0].split("=").collect();
-                    let group_name: &str = group_name[1];
-                    log::debug!("Group Name: {}", group_name);
-
-                    let perm = Permission {
-                        name: group_name.to_string(),
-                        description: entry.dn.clone(),
-                        access_type: Access::READ,
-                    };
-
-                    permissions.push(perm);
+    /// # Arguments
+    /// * `user_dn` - The DN of the user.
+    /// * `direct_groups` - The direct group memberships of the user.
+    /// # Returns
+    /// * The DNs of all groups of the user. If a lookup fails, the groups found so far.
+    async fn nested_groups(&mut self, user_dn: &str, direct_groups: Vec<String>) -> Vec<String> {
+        match CONFIG.ldap_nested_groups {
+            NestedGroups::None => direct_groups,
+            NestedGroups::InChain => {
+                let found = self.search_groups(&groups::in_chain_filter(user_dn)).await;
+                groups::merge(direct_groups, found)
+            }
+            NestedGroups::Walk => {
+                let mut walk = GroupWalk::new(user_dn, direct_groups, CONFIG.ldap_nested_groups_max_depth);
+                while let Some(frontier) = walk.frontier() {
+                    let filter = groups::member_filter(&CONFIG.ldap_group_member_filter, frontier);
+                    let found = self.search_groups(&filter).await;
+                    walk.visit(found);
                 }
+                walk.into_groups()
             }
         }
-        permissions
     }
 
-    /// Create a new LDAP connection
+    /// Search `LDAP_GROUP_SEARCH_BASE` and return the DNs of the groups found.
     ///
-    /// Static function to create a new LDAP connection.
-    /// Returns a Result object containing the LDAP connection and the LDAP object.
-    pub async fn initialize(&mut self) -> bool {
-        let (conn, ldap) = match LdapConnAsync::new(&CONFIG.ldap_url).await {
-            Ok((conn, ldap)) => {
-                log::info!("Connection established.");
-                (conn, ldap)
-            }
-            Err(err) => {
-                log::error!(
-                    "Could not establish a connection to LDAP server {}: {}",
-                    &CONFIG.ldap_url,
-                    err
-                );
-                return false;
-            }
+    /// A failed search is remembered in `group_search_failure`, so the permissions are known to be partial.
+    async fn search_groups(&mut self, filter: &str) -> Vec<String> {
+        let base = match &CONFIG.ldap_group_search_base {
+            Some(base) => base,
+            None => return vec![],
         };
+        log::debug!("Group search base DN: {}", base);
+        log::debug!("Group filter: {:?}", filter);
 
-        drive!(conn);
-        self.ldap = Some(ldap);
-        true
-    }
-
-    pub async fn unbind_ldap(&mut self) -> () {
-        let ldap = match self.ldap.as_mut() {
+        let ldap: &mut Ldap = match self.ldap.as_mut() {
             Some(ldap) => ldap,
             None => {
                 log::warn!("LDAP connection not initialized");
-                return;
+                self.group_search_failure = Some("LDAP connection not initialized".to_string());
+                return vec![];
             }
         };
 
-        match ldap.unbind().await {
-            Ok(_) => {
-                log::info!("Connection closed.");
-            }
+        // "1.1" requests no attributes, only the DN is needed
+        let attrs = match group_attrs() {
+            attrs if attrs.is_empty() => vec!["1.1"],
+            attrs => attrs,
+        };
+        let search_result = ldap.search(base, Scope::Subtree, filter, attrs).await;
+
+        // A failed search has been logged, the groups found by other searches still count
+        let entries = match self.unpack_search_results(search_result).await {
+            Ok(entries) => entries,
             Err(err) => {
-                log::error!("Failed to unbind from LDAP server: {:?}", err);
+                self.group_search_failure = Some(err.to_string());
+                vec![]
             }
+        };
+        let mut dns = vec![];
+        for entry in entries {
+            self.remember_group(&entry);
+            dns.push(entry.dn);
         }
+        dns
     }
 
+    /// Read the attributes of groups that are part of their permissions: the description and stable identifier.
+    ///
+    /// Groups found by `search_groups` already carry their attributes. The entries of the other groups are
+    /// read in batches of concurrent requests, so a user in many groups does not cause as many sequential
+    /// round trips.
+    ///
+    /// # Arguments
+    /// * `dns` - The DNs of the groups.
+    /// # Returns
+    /// * The groups, without the attributes that are not configured or could not be read. A failed read is
+    ///   remembered in `group_search_failure`, as the permissions of the group may be incomplete.
+    async fn read_groups(&mut self, dns: Vec<String>) -> Vec<Group> {
+        let attrs = group_attrs();
+        if !attrs.is_empty() {
+            let missing: Vec<&String> = dns
+                .iter()
+                .filter(|dn| !self.group_entries.contains_key(&dn.to_lowercase()))
+                .collect();
+
+            if let Some(connection) = self.ldap.as_ref() {
+                let mut entries = vec![];
+                for batch in missing.chunks(GROUP_BATCH_SIZE) {
+                    let lookups = batch.iter().map(|dn| {
+                        // Requests on clones of the handle are multiplexed over the same connection
+                        let mut ldap: Ldap = (**connection).clone();
+                        let attrs = attrs.clone();
+                        async move { ldap.search(dn, Scope::Base, "(objectClass=*)", attrs).await }
+                    });
+
+                    for (dn, result) in batch.iter().zip(join_all(lookups).await) {
+                        match result.and_then(|result| result.success()) {
+                            Ok((found, _)) => entries.extend(found.into_iter().map(SearchEntry::construct)),
+                            Err(err) => {
+                                log::warn!("Could not read group {}: {}", dn, err);
+                                self.group_search_failure = Some(format!("Could not read group {}: {}", dn, err));
+                            }
+                        }
+                    }
+                }
+                for entry in entries {
+                    self.remember_group(&entry);
+                }
+            }
+        }
+
+        dns.into_iter()
+            .map(|dn| match self.group_entries.get(&dn.to_lowercase()) {
+                Some(group) => Group { dn, ..group.clone() },
+                None => Group {
+                    dn,
+                    description: None,
+                    id: None,
+                },
+            })
+            .collect()
+    }
+
+    /// Keep the attributes of a group entry for `read_groups`
+    fn remember_group(&mut self, entry: &SearchEntry) {
+        let description = match CONFIG.ldap_group_descriptions {
+            true => groups::attribute(entry, &CONFIG.ldap_group_description_attr).map(str::to_string),
+            false => None,
+        };
+        let id = CONFIG.ldap_permission_id.as_ref().and_then(|kind| object_id::read(entry, kind));
+
+        self.group_entries.insert(
+            entry.dn.to_lowercase(),
+            Group {
+                dn: entry.dn.clone(),
+                description,
+                id,
+            },
+        );
+    }
+
+    /// Check out a connection from the pool
+    ///
+    /// Returns an `AuthError` if no connection is available.
+    pub async fn initialize(&mut self) -> Result<(), AuthError> {
+        self.ldap = self.pool.get().await;
+        match self.ldap {
+            Some(_) => Ok(()),
+            None => Err(AuthError::BackendUnavailable("No LDAP connection available".to_string())),
+        }
+    }
+
+    /// The connection checked out by `initialize`
+    fn connection(&mut self) -> Result<&mut Ldap, AuthError> {
+        match self.ldap.as_mut() {
+            Some(ldap) => Ok(ldap),
+            None => {
+                log::error!("LDAP connection not initialized");
+                Err(AuthError::Internal("LDAP connection not initialized".to_string()))
+            }
+        }
+    }
+
+    /// Return the connection to the pool
+    ///
+    /// The pool binds the connection as the service identity again, so the next request does not act as the
+    /// user that authenticated on it.
+    pub async fn release_connection(&mut self) {
+        match self.ldap.take() {
+            Some(connection) => self.pool.release(connection).await,
+            None => log::warn!("LDAP connection not initialized"),
+        }
+    }
+
+    /// Unpack the entries of a search
+    ///
+    /// A search base that does not exist is not an error, it has no entries.
+    ///
+    /// # Returns
+    /// * The entries, or an `AuthError` if the search failed
     pub(crate) async fn unpack_search_results(
         &self,
         search_result: Result<SearchResult, LdapError>,
-    ) -> Vec<SearchEntry> {
+    ) -> Result<Vec<SearchEntry>, AuthError> {
         let entries = match search_result {
+            Ok(result) if result.1.rc == NO_SUCH_OBJECT => {
+                log::debug!("No results: {}", result.1);
+                vec![]
+            }
             Ok(result) => match result.success() {
                 Ok((entries, _)) => {
                     log::info!(
@@ -183,12 +387,12 @@ impl LdapConnector {
                 }
                 Err(e) => {
                     log::error!("No results: {}", e);
-                    vec![]
+                    return Err(AuthError::BackendUnavailable(e.to_string()));
                 }
             },
             Err(e) => {
                 log::error!("LdapError: {}", e);
-                vec![]
+                return Err(AuthError::BackendUnavailable(e.to_string()));
             }
         };
 
@@ -199,26 +403,158 @@ impl LdapConnector {
             search_entries.push(SearchEntry::construct(entry));
         }
 
-        search_entries
+        Ok(search_entries)
+    }
+
+    /// The DN a user binds as in `direct` mode.
+    ///
+    /// The username is escaped according to RFC 4514, so it can not add RDNs or change the base DN.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user.
+    /// # Returns
+    /// * `CN={username},{AD_BASE_DN}`
+    pub fn direct_bind_dn(username: &str) -> String {
+        format!("CN={},{}", dn_escape(username), CONFIG.ad_base_dn)
+    }
+
+    /// The filter used to search for a user in `search` mode.
+    ///
+    /// The username is escaped according to RFC 4515, so it can not add wildcards or filter components.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user.
+    /// # Returns
+    /// * `LDAP_USER_FILTER` with `{username}` replaced by the escaped username
+    pub fn user_search_filter(username: &str) -> String {
+        CONFIG.ldap_user_filter.replace("{username}", &ldap_escape(username))
+    }
+
+    /// Find the DN of a user.
+    ///
+    /// In `direct` mode the DN is `CN={username},{AD_BASE_DN}`. In `search` mode the connector binds as the
+    /// service account and searches `LDAP_USER_SEARCH_BASE` with `LDAP_USER_FILTER`, in which `{username}`
+    /// is replaced by the username. Usernames rejected by `validate_username` are never looked up.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user.
+    /// # Returns
+    /// * The DN of the user, or None if the user was not found or the search matched more than one entry.
+    ///   An `AuthError` if the search failed.
+    pub(crate) async fn find_user_dn(&mut self, username: &str) -> Result<Option<String>, AuthError> {
+        if let Err(err) = validate_username(username) {
+            log::warn!("Refusing to look up user: {}", err);
+            return Ok(None);
+        }
+
+        if CONFIG.ldap_auth_mode == LdapAuthMode::Direct {
+            return Ok(Some(Self::direct_bind_dn(username)));
+        }
+
+        if let Some((user, dn)) = &self.user_dn {
+            if user == username {
+                return Ok(Some(dn.clone()));
+            }
+        }
+
+        self.search_user_dn(username).await
+    }
+
+    /// Search for the DN of a user as the service account, as `find_user_dn` does in `search` mode.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user.
+    /// # Returns
+    /// * The DN of the user, or None if the user was not found or the search matched more than one entry.
+    ///   An `AuthError` if the bind as the service account or the search failed.
+    pub async fn search_user_dn(&mut self, username: &str) -> Result<Option<String>, AuthError> {
+        // The connection may still be bound as the last user that authenticated
+        self.bind_service_account().await?;
+
+        let filter = Self::user_search_filter(username);
+        log::debug!("User search base DN: {}", &CONFIG.ldap_user_search_base);
+        log::debug!("User filter: {:?}", filter);
+
+        let ldap = self.connection()?;
+
+        // "1.1" requests no attributes, only the DN is needed
+        let search_result = ldap
+            .search(&CONFIG.ldap_user_search_base, Scope::Subtree, &filter, vec!["1.1"])
+            .await;
+        let entries = self.unpack_search_results(search_result).await?;
+
+        let dn = Self::unique_user_dn(username, entries);
+        if let Some(dn) = &dn {
+            self.user_dn = Some((username.to_string(), dn.clone()));
+        }
+        Ok(dn)
+    }
+
+    /// The DN of the user found by the user search.
+    ///
+    /// A filter that matches several entries is ambiguous, binding as any of them could log the user in as
+    /// someone else.
+    ///
+    /// # Arguments
+    /// * `username` - The username of the user.
+    /// * `entries` - The entries found by the search.
+    /// # Returns
+    /// * The DN of the only entry, or None if there is no entry or more than one.
+    pub fn unique_user_dn(username: &str, mut entries: Vec<SearchEntry>) -> Option<String> {
+        match entries.len() {
+            0 => {
+                log::debug!("User not found: {}", username);
+                None
+            }
+            1 => {
+                let dn = entries.remove(0).dn;
+                log::debug!("Found user {} as {}", username, dn);
+                Some(dn)
+            }
+            count => {
+                log::error!("User filter matched {} entries for user {}, refusing to bind", count, username);
+                None
+            }
+        }
+    }
+
+    /// Bind as the service account configured by `LDAP_BIND_DN` and `LDAP_BIND_PASSWORD`.
+    ///
+    /// Without a service account the connection is bound anonymously, so searches are anonymous.
+    ///
+    /// # Returns
+    /// * An `AuthError` if the bind failed.
+    async fn bind_service_account(&mut self) -> Result<(), AuthError> {
+        match bind_service_identity(self.connection()?).await {
+            true => Ok(()),
+            false => Err(AuthError::BackendUnavailable("Bind as the service identity failed".to_string())),
+        }
     }
 
     /// Lookup the permissions for a user.
-    pub(crate) async fn permission_lookup(&mut self, identifier: &str) -> Vec<SearchEntry> {
+    ///
+    /// In `search` mode the lookup runs as the service account, not as the user that just authenticated.
+    ///
+    /// # Returns
+    /// * The entry of the user, none if the user does not exist. An `AuthError` if the lookup failed.
+    pub(crate) async fn permission_lookup(&mut self, identifier: &str) -> Result<Vec<SearchEntry>, AuthError> {
         let filter: &str = &CONFIG.ad_filter_format;
-        let attrs: Vec<String> = CONFIG.ad_attrs.clone();
-        let bind_dn = format!("CN={},{}", identifier, CONFIG.ad_base_dn);
+        let mut attrs: Vec<String> = CONFIG.ad_attrs.clone();
+        attrs.extend(ACCOUNT_STATUS_ATTRS.iter().map(|attr| attr.to_string()));
+        let bind_dn = match self.find_user_dn(identifier).await? {
+            Some(bind_dn) => bind_dn,
+            None => return Ok(vec![]),
+        };
+
+        if CONFIG.ldap_auth_mode == LdapAuthMode::Search {
+            self.bind_service_account().await?;
+        }
 
         log::debug!("Search base DN: {}", &CONFIG.ad_base_dn);
         log::debug!("Filter: {:?}", filter);
         log::debug!("Attributes: {:?}", attrs);
 
-        let ldap: &mut Ldap = match self.ldap.as_mut() {
-            Some(ldap) => ldap,
-            None => {
-                log::warn!("LDAP connection not initialized");
-                return vec![];
-            }
-        };
+        let ldap = self.connection()?;
 
         let search_result: Result<SearchResult, LdapError> =
             ldap.search(&bind_dn, Scope::Subtree, filter, attrs).await;
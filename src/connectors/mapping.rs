@@ -0,0 +1,251 @@
+use crate::config::PermissionName;
+use crate::connectors::dn::Dn;
+use crate::connectors::groups::Group;
+use crate::models::{Access, Permission};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::fs;
+
+/// Rules that map groups to the permissions they grant
+///
+/// The rules are read from a JSON file once at startup:
+///
+/// ```json
+/// {
+///   "drop_unmapped": true,
+///   "rules": [
+///     { "dn": "CN=tool1,OU=tools,DC=example,DC=com", "permissions": [{ "name": "tool1", "access": "WRITE" }] },
+///     {
+///       "cn": "tool2",
+///       "permissions": [
+///         { "name": "tool2" },
+///         { "name": "tool2-admin", "access": "EXECUTE", "description": "Tool 2 administration" }
+///       ]
+///     },
+///     { "regex": "^CN=app-([^,]+)-admins,", "permissions": [{ "name": "$1", "access": "EXECUTE" }] }
+///   ]
+/// }
+/// ```
+///
+/// A rule matches a group by its full DN, compared as parsed DN, or its CN, both case-insensitive, or by a
+/// case-insensitive regular expression on the DN. The permission names of a regex rule can refer to its capture groups.
+/// Permissions whose name expands to an empty string are skipped. Every matching rule applies, the access defaults to
+/// READ, and the description to the description of the group.
+///
+/// Groups no rule matches grant READ access to a permission named after the group as configured by
+/// `PermissionName`, unless `drop_unmapped` is set. Without a mapping file every group is mapped this way.
+#[derive(Default)]
+pub struct GroupMapping {
+    drop_unmapped: bool,
+    rules: Vec<MappingRule>,
+    naming: PermissionName,
+}
+
+struct MappingRule {
+    matcher: Matcher,
+    permissions: Vec<MappedPermission>,
+}
+
+enum Matcher {
+    Dn(Dn),
+    Cn(String),
+    Regex(Regex),
+}
+
+#[derive(Deserialize)]
+struct MappedPermission {
+    name: String,
+    #[serde(default = "default_access")]
+    access: Access,
+    description: Option<String>,
+}
+
+fn default_access() -> Access {
+    Access::READ
+}
+
+#[derive(Deserialize)]
+struct MappingFile {
+    #[serde(default)]
+    drop_unmapped: bool,
+    #[serde(default)]
+    rules: Vec<RuleDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    dn: Option<String>,
+    cn: Option<String>,
+    regex: Option<String>,
+    permissions: Vec<MappedPermission>,
+}
+
+impl GroupMapping {
+    /// Parse a mapping file
+    ///
+    /// # Arguments
+    /// * `json` - The content of the mapping file
+    /// # Returns
+    /// * The mapping, or a description of why the file is invalid
+    pub fn parse(json: &str) -> Result<GroupMapping, String> {
+        let file: MappingFile = serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let matcher = match (rule.dn, rule.cn, rule.regex) {
+                    (Some(dn), None, None) => Matcher::Dn(
+                        dn.parse().map_err(|err| format!("Invalid DN in rule {}: {}", index, err))?,
+                    ),
+                    (None, Some(cn), None) => Matcher::Cn(cn),
+                    // Servers differ in the case of attribute types, e.g. `CN=` in Active Directory, `cn=` in OpenLDAP
+                    (None, None, Some(regex)) => Matcher::Regex(
+                        RegexBuilder::new(&regex)
+                            .case_insensitive(true)
+                            .build()
+                            .map_err(|err| format!("Invalid regex in rule {}: {}", index, err))?,
+                    ),
+                    _ => return Err(format!("Rule {} must have exactly one of dn, cn or regex", index)),
+                };
+                Ok(MappingRule { matcher, permissions: rule.permissions })
+            })
+            .collect::<Result<Vec<MappingRule>, String>>()?;
+
+        Ok(GroupMapping {
+            drop_unmapped: file.drop_unmapped,
+            rules,
+            naming: PermissionName::default(),
+        })
+    }
+
+    /// Set what the permissions of unmapped groups are named after
+    pub fn with_naming(mut self, naming: PermissionName) -> GroupMapping {
+        self.naming = naming;
+        self
+    }
+
+    /// Read and parse a mapping file
+    ///
+    /// # Arguments
+    /// * `path` - The path of the mapping file
+    pub fn load(path: &str) -> Result<GroupMapping, String> {
+        let json = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+        GroupMapping::parse(&json).map_err(|err| format!("Invalid group mapping file {}: {}", path, err))
+    }
+
+    /// Map groups to the permissions they grant
+    ///
+    /// The permissions are described by the description of the group that grants them, or its DN if it has
+    /// no description, and carry the stable identifier of the group if it has been looked up. Permissions of
+    /// different groups with the same name are only merged if the groups have no identifier.
+    ///
+    /// # Arguments
+    /// * `groups` - The groups
+    /// # Returns
+    /// * The permissions, without duplicates
+    pub fn permissions(&self, groups: &[Group]) -> Vec<Permission> {
+        let mut permissions: Vec<Permission> = vec![];
+
+        for group in groups {
+            let description = group.description.as_deref().unwrap_or(&group.dn);
+            for permission in self.group_permissions(&group.dn, description, &group.id) {
+                let duplicate = permissions.iter().any(|p| {
+                    p.name == permission.name && p.access_type == permission.access_type && p.id == permission.id
+                });
+                if !duplicate {
+                    permissions.push(permission);
+                }
+            }
+        }
+        permissions
+    }
+
+    /// The permissions granted by a single group
+    fn group_permissions(&self, group: &str, description: &str, id: &Option<String>) -> Vec<Permission> {
+        let mut permissions = vec![];
+        let mut mapped = false;
+
+        let dn: Dn = match group.parse() {
+            Ok(dn) => dn,
+            Err(err) => {
+                log::warn!("Ignoring group with invalid DN {}: {}", group, err);
+                return permissions;
+            }
+        };
+
+        for rule in &self.rules {
+            let names: Vec<String> = match &rule.matcher {
+                Matcher::Dn(rule_dn) if rule_dn.matches(&dn) => {
+                    rule.permissions.iter().map(|p| p.name.clone()).collect()
+                }
+                Matcher::Cn(cn) if leaf_cn(&dn).is_some_and(|name| name.eq_ignore_ascii_case(cn)) => {
+                    rule.permissions.iter().map(|p| p.name.clone()).collect()
+                }
+                Matcher::Regex(regex) => match regex.captures(group) {
+                    Some(captures) => rule
+                        .permissions
+                        .iter()
+                        .map(|p| {
+                            let mut name = String::new();
+                            captures.expand(&p.name, &mut name);
+                            name
+                        })
+                        .collect(),
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            mapped = true;
+            for (name, permission) in names.into_iter().zip(&rule.permissions) {
+                // e.g. `$1_admin`, which refers to a capture group named `1_admin` instead of `${1}_admin`
+                if name.is_empty() {
+                    log::warn!("Skipping permission {:?} of group {}: the name is empty", permission.name, group);
+                    continue;
+                }
+                permissions.push(Permission {
+                    name,
+                    description: permission.description.as_deref().unwrap_or(description).to_string(),
+                    access_type: permission.access.clone(),
+                    id: id.clone(),
+                });
+            }
+        }
+
+        if !mapped {
+            if self.drop_unmapped {
+                log::debug!("Dropping unmapped group: {}", group);
+            } else if let Some(name) = self.name(group, &dn) {
+                permissions.push(Permission {
+                    name,
+                    description: description.to_string(),
+                    access_type: Access::READ,
+                    id: id.clone(),
+                });
+            } else {
+                log::warn!("Ignoring group without a name as configured by LDAP_PERMISSION_NAME: {}", group);
+            }
+        }
+        permissions
+    }
+
+    /// The name of the permission granted by an unmapped group
+    fn name(&self, group: &str, dn: &Dn) -> Option<String> {
+        match &self.naming {
+            PermissionName::Cn => leaf_cn(dn).map(str::to_string),
+            PermissionName::Dn => Some(group.to_string()),
+            PermissionName::Rdn(attr_type) => dn.value(attr_type).map(str::to_string),
+        }
+    }
+}
+
+/// The CN of the leaf RDN, e.g. `tool1` for `CN=tool1,OU=tools,DC=example,DC=com`. Falls back to the first
+/// value of the leaf RDN if it has no CN.
+fn leaf_cn(dn: &Dn) -> Option<&str> {
+    let leaf = dn.leaf()?;
+    leaf.value("cn")
+        .or_else(|| leaf.attributes.first().map(|attribute| attribute.value.as_str()))
+}
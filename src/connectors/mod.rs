@@ -1,4 +1,16 @@
 pub mod ldap;
+pub mod mapping;
 pub mod connector;
+pub mod diagnostics;
+pub mod dn;
+pub mod dummy;
+pub mod groups;
+pub mod object_id;
+pub mod pool;
+pub mod registry;
+pub mod servers;
+pub mod tls;
+pub mod username;
 
 pub use connector::Connector;
+pub use registry::ConnectorRegistry;
@@ -0,0 +1,79 @@
+use crate::config::PermissionId;
+use ldap3::SearchEntry;
+
+/// Read the stable identifier of an entry
+///
+/// `objectGUID` and `objectSid` are binary and formatted in their usual string representation, `entryUUID` is
+/// already a string.
+///
+/// # Arguments
+/// * `entry` - The entry, searched with the attribute of `kind`
+/// * `kind` - The identifier to read
+/// # Returns
+/// * The identifier, or None if the entry does not have it or it is malformed
+pub fn read(entry: &SearchEntry, kind: &PermissionId) -> Option<String> {
+    let value = binary_attribute(entry, kind.attribute())?;
+    let id = match kind {
+        PermissionId::ObjectGuid => format_guid(&value),
+        PermissionId::ObjectSid => format_sid(&value),
+        PermissionId::EntryUuid => String::from_utf8(value).ok().map(|uuid| uuid.to_lowercase()),
+    };
+    if id.is_none() {
+        log::warn!("Ignoring malformed {} of {}", kind.attribute(), entry.dn);
+    }
+    id
+}
+
+/// The first value of an attribute as bytes
+///
+/// ldap3 only moves values that are not valid UTF-8 to `bin_attrs`, so a binary value can be in either map.
+fn binary_attribute(entry: &SearchEntry, attr_type: &str) -> Option<Vec<u8>> {
+    let text = entry
+        .attrs
+        .iter()
+        .find(|(attr, _)| attr.eq_ignore_ascii_case(attr_type))
+        .and_then(|(_, values)| values.first())
+        .map(|value| value.as_bytes().to_vec());
+    let binary = || {
+        entry
+            .bin_attrs
+            .iter()
+            .find(|(attr, _)| attr.eq_ignore_ascii_case(attr_type))
+            .and_then(|(_, values)| values.first())
+            .cloned()
+    };
+    text.or_else(binary)
+}
+
+/// Format a binary GUID, e.g. `3f2504e0-4f89-11d3-9a0c-0305e82c3301`
+///
+/// The first three fields are stored little-endian, the rest in byte order.
+pub fn format_guid(bytes: &[u8]) -> Option<String> {
+    let b: &[u8; 16] = bytes.try_into().ok()?;
+    Some(format!(
+        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+    ))
+}
+
+/// Format a binary security identifier, e.g. `S-1-5-21-1004336348-1177238915-682003330-512`
+///
+/// A SID is a revision byte, the number of sub-authorities, a 48 bit big-endian identifier authority and the
+/// sub-authorities as 32 bit little-endian integers.
+pub fn format_sid(bytes: &[u8]) -> Option<String> {
+    if bytes.len() < 8 {
+        return None;
+    }
+    let count = bytes[1] as usize;
+    if bytes.len() != 8 + 4 * count {
+        return None;
+    }
+
+    let authority = bytes[2..8].iter().fold(0u64, |authority, byte| authority << 8 | *byte as u64);
+    let mut sid = format!("S-{}-{}", bytes[0], authority);
+    for sub_authority in bytes[8..].chunks_exact(4) {
+        let value = u32::from_le_bytes([sub_authority[0], sub_authority[1], sub_authority[2], sub_authority[3]]);
+        sid.push_str(&format!("-{}", value));
+    }
+    Some(sid)
+}
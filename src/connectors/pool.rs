@@ -0,0 +1,321 @@
+use crate::config::CONFIG;
+use crate::connectors::servers::ServerSet;
+use crate::connectors::tls;
+use ldap3::{drive, Ldap, LdapConnAsync, LdapConnSettings, Scope};
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How long a health check may take before the connection is considered broken
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pool of LDAP connections shared across requests
+///
+/// Opening a connection, and eventually negotiating TLS, is the slowest part of a login. The pool keeps
+/// connections open between requests instead.
+///
+/// Connections are handed out bound as the service identity: the service account if `LDAP_BIND_DN` is set,
+/// otherwise anonymous. A connector may bind as a user, so connections are bound as the service identity
+/// again before they are returned to the pool. A connection that is dropped without being released is closed.
+///
+/// New connections are opened to the first server of the `ServerSet` that accepts them. Servers that refuse a
+/// connection or the service bind, or whose idle connections fail a health check, are marked unhealthy.
+pub struct LdapPool {
+    servers: ServerSet,
+    settings: LdapConnSettings,
+    min_size: usize,
+    max_size: usize,
+    idle_timeout: Duration,
+    acquire_timeout: Duration,
+    idle: Mutex<VecDeque<IdleConnection>>,
+    permits: Arc<Semaphore>,
+}
+
+struct IdleConnection {
+    ldap: Ldap,
+    url: String,
+    idle_since: Instant,
+}
+
+/// A connection checked out of the pool
+///
+/// Dereferences to the `Ldap` handle. Return it with `LdapPool::release`.
+pub struct PooledConnection {
+    ldap: Ldap,
+    url: String,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+    type Target = Ldap;
+
+    fn deref(&self) -> &Ldap {
+        &self.ldap
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Ldap {
+        &mut self.ldap
+    }
+}
+
+impl LdapPool {
+    /// Create an empty pool
+    ///
+    /// # Arguments
+    /// * `servers` - The LDAP servers
+    /// * `settings` - The connection settings, e.g. TLS
+    /// * `min_size` - The number of idle connections kept open by `maintain`
+    /// * `max_size` - The maximum number of connections in use at the same time
+    /// * `idle_timeout` - How long a connection may be idle before it is closed
+    /// * `acquire_timeout` - How long to wait for a connection when all of them are in use
+    pub fn new(
+        servers: ServerSet,
+        settings: LdapConnSettings,
+        min_size: usize,
+        max_size: usize,
+        idle_timeout: Duration,
+        acquire_timeout: Duration,
+    ) -> LdapPool {
+        LdapPool {
+            servers,
+            settings,
+            min_size,
+            max_size,
+            idle_timeout,
+            acquire_timeout,
+            idle: Mutex::new(VecDeque::new()),
+            permits: Arc::new(Semaphore::new(max_size)),
+        }
+    }
+
+    /// Create an empty pool configured by the `LDAP_POOL_*`, `LDAP_TLS_*` and server variables
+    ///
+    /// # Panics
+    /// If the TLS configuration is invalid, e.g. the CA bundle can not be read.
+    pub fn from_config() -> LdapPool {
+        let settings = match tls::conn_settings() {
+            Ok(settings) => settings,
+            Err(err) => panic!("Invalid LDAP TLS configuration: {}", err),
+        };
+        // A server that is down may not refuse connections, but never answer
+        let settings = settings.set_conn_timeout(Duration::from_secs(CONFIG.ldap_connect_timeout_seconds));
+
+        LdapPool::new(
+            ServerSet::from_config(),
+            settings,
+            CONFIG.ldap_pool_min_size,
+            CONFIG.ldap_pool_max_size,
+            Duration::from_secs(CONFIG.ldap_pool_idle_timeout_seconds),
+            Duration::from_secs(CONFIG.ldap_pool_acquire_timeout_seconds),
+        )
+    }
+
+    /// Check out a connection
+    ///
+    /// Reuses the most recently released idle connection, or opens a new one if there is none.
+    ///
+    /// # Returns
+    /// * The connection, or None if no connection could be opened or all connections stayed in use for
+    ///   longer than the acquire timeout
+    pub async fn get(&self) -> Option<PooledConnection> {
+        let permit = match tokio::time::timeout(self.acquire_timeout, self.permits.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => permit,
+            Ok(Err(_)) => return None,
+            Err(_) => {
+                log::error!("Timed out waiting for a free LDAP connection, all {} are in use", self.max_size);
+                return None;
+            }
+        };
+
+        while let Some(mut idle) = self.pop_idle() {
+            if idle.idle_since.elapsed() >= self.idle_timeout || idle.ldap.is_closed() {
+                Self::close(idle.ldap).await;
+                continue;
+            }
+            return Some(PooledConnection {
+                ldap: idle.ldap,
+                url: idle.url,
+                _permit: permit,
+            });
+        }
+
+        let (ldap, url) = self.connect().await?;
+        Some(PooledConnection { ldap, url, _permit: permit })
+    }
+
+    /// Return a connection to the pool
+    ///
+    /// The connection is bound as the service identity again first. If that fails, it is closed instead.
+    pub async fn release(&self, mut connection: PooledConnection) {
+        if connection.ldap.is_closed() || !bind_service_identity(&mut connection.ldap).await {
+            Self::close(connection.ldap).await;
+            return;
+        }
+
+        self.idle.lock().unwrap().push_back(IdleConnection {
+            ldap: connection.ldap,
+            url: connection.url,
+            idle_since: Instant::now(),
+        });
+    }
+
+    /// Close idle connections that timed out or fail a health check, then open connections until
+    /// `min_size` connections are idle
+    pub async fn maintain(&self) {
+        let idle: Vec<IdleConnection> = self.idle.lock().unwrap().drain(..).collect();
+
+        let mut healthy = VecDeque::new();
+        for mut connection in idle {
+            if connection.idle_since.elapsed() >= self.idle_timeout {
+                Self::close(connection.ldap).await;
+            } else if !Self::is_healthy(&mut connection.ldap).await {
+                self.servers.mark_unhealthy(&connection.url);
+                Self::close(connection.ldap).await;
+            } else {
+                healthy.push_back(connection);
+            }
+        }
+
+        // Connections released in the meantime are more recent, keep them at the back
+        {
+            let mut idle = self.idle.lock().unwrap();
+            while let Some(connection) = healthy.pop_back() {
+                idle.push_front(connection);
+            }
+        }
+
+        while self.idle_count() < self.min_size && self.idle_count() + self.in_use() < self.max_size {
+            match self.connect().await {
+                Some((ldap, url)) => self.idle.lock().unwrap().push_back(IdleConnection {
+                    ldap,
+                    url,
+                    idle_since: Instant::now(),
+                }),
+                None => break,
+            }
+        }
+    }
+
+    /// The number of idle connections
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// The number of connections checked out
+    pub fn in_use(&self) -> usize {
+        self.max_size - self.permits.available_permits()
+    }
+
+    fn pop_idle(&self) -> Option<IdleConnection> {
+        self.idle.lock().unwrap().pop_back()
+    }
+
+    /// The servers new connections are opened to
+    pub fn servers(&self) -> &ServerSet {
+        &self.servers
+    }
+
+    /// Open a new connection, bound as the service identity
+    ///
+    /// The servers are tried in the order of `ServerSet::candidates` until one accepts the connection and the
+    /// bind as the service identity.
+    ///
+    /// # Returns
+    /// * The connection and the URL of its server, or None if no server accepted a connection
+    async fn connect(&self) -> Option<(Ldap, String)> {
+        for url in self.servers.candidates().await {
+            let (conn, mut ldap) = match LdapConnAsync::with_settings(self.settings.clone(), &url).await {
+                Ok((conn, ldap)) => {
+                    log::info!("Connection established to {}.", url);
+                    (conn, ldap)
+                }
+                Err(err) => {
+                    log::error!("Could not establish a connection to LDAP server {}: {}", url, err);
+                    self.servers.mark_unhealthy(&url);
+                    continue;
+                }
+            };
+            drive!(conn);
+
+            // A server that accepts connections, but can not bind, e.g. because it is busy, is no use either
+            if !bind_service_identity(&mut ldap).await {
+                self.servers.mark_unhealthy(&url);
+                Self::close(ldap).await;
+                continue;
+            }
+            self.servers.mark_healthy(&url);
+            return Some((ldap, url));
+        }
+
+        log::error!("No LDAP server accepted a connection");
+        None
+    }
+
+    /// Read the root DSE, which every LDAP server allows
+    async fn is_healthy(ldap: &mut Ldap) -> bool {
+        if ldap.is_closed() {
+            return false;
+        }
+
+        let result = ldap
+            .with_timeout(HEALTH_CHECK_TIMEOUT)
+            .search("", Scope::Base, "(objectClass=*)", vec!["1.1"])
+            .await;
+
+        match result.map(|result| result.success()) {
+            Ok(Ok(_)) => true,
+            Ok(Err(err)) | Err(err) => {
+                log::warn!("LDAP connection failed health check: {}", err);
+                false
+            }
+        }
+    }
+
+    async fn close(mut ldap: Ldap) {
+        if ldap.is_closed() {
+            return;
+        }
+        match ldap.unbind().await {
+            Ok(_) => log::info!("Connection closed."),
+            Err(err) => log::error!("Failed to unbind from LDAP server: {:?}", err),
+        }
+    }
+}
+
+/// Bind as the service account configured by `LDAP_BIND_DN` and `LDAP_BIND_PASSWORD`, or anonymously if
+/// there is none.
+///
+/// # Returns
+/// * `true` if the bind succeeded, `false` otherwise.
+pub(crate) async fn bind_service_identity(ldap: &mut Ldap) -> bool {
+    let (bind_dn, password) = match (&CONFIG.ldap_bind_dn, &CONFIG.ldap_bind_password) {
+        (Some(bind_dn), Some(password)) => (bind_dn.as_str(), password.as_str()),
+        _ => ("", ""),
+    };
+
+    match ldap.simple_bind(bind_dn, password).await.map(|res| res.success()) {
+        Ok(Ok(_)) => true,
+        Ok(Err(err)) | Err(err) => {
+            log::error!("Bind as service identity {:?} failed: {}", bind_dn, err);
+            false
+        }
+    }
+}
+
+/// Run `LdapPool::maintain` at a fixed interval
+///
+/// # Arguments
+/// * `pool` - The pool to maintain
+/// * `interval_seconds` - The time between two runs
+pub async fn maintain_on_schedule(pool: Arc<LdapPool>, interval_seconds: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
+    // The first tick completes immediately, which opens the minimum number of connections at startup
+    loop {
+        interval.tick().await;
+        pool.maintain().await;
+    }
+}
@@ -0,0 +1,84 @@
+use crate::config::CONFIG;
+use crate::connectors::dummy::{DummyConnector, DummyDirectory};
+use crate::connectors::ldap::LdapConnector;
+use crate::connectors::mapping::GroupMapping;
+use crate::connectors::pool::LdapPool;
+use crate::connectors::Connector;
+use crate::traits::auth::Auth;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type Factory = Box<dyn Fn() -> Box<dyn Auth> + Send + Sync>;
+
+/// Maps each `Connector` to the implementation that handles it
+///
+/// Connectors hold per-request state such as an open LDAP connection, so the registry stores a factory
+/// per connector and every request gets a fresh instance.
+#[derive(Default)]
+pub struct ConnectorRegistry {
+    factories: HashMap<Connector, Factory>,
+}
+
+impl ConnectorRegistry {
+    /// Create an empty registry
+    pub fn new() -> ConnectorRegistry {
+        ConnectorRegistry::default()
+    }
+
+    /// Create a registry with the connectors enabled by the configuration
+    ///
+    /// The LDAP connector is always enabled, mapping groups to permissions with `LDAP_GROUP_MAPPING_FILE` if
+    /// set. The dummy connector is enabled if `DUMMY_USERS_FILE` is set.
+    ///
+    /// # Arguments
+    /// * `ldap_pool` - The connection pool shared by all instances of the LDAP connector
+    ///
+    /// # Panics
+    /// If the group mapping file or the users file of the dummy connector can not be loaded.
+    pub fn from_config(ldap_pool: Arc<LdapPool>) -> ConnectorRegistry {
+        let mut registry = ConnectorRegistry::new();
+
+        let mapping = match &CONFIG.ldap_group_mapping_file {
+            Some(path) => match GroupMapping::load(path) {
+                Ok(mapping) => mapping,
+                Err(err) => panic!("Could not load the group mapping: {}", err),
+            },
+            None => GroupMapping::default(),
+        };
+        let mapping = Arc::new(mapping.with_naming(CONFIG.ldap_permission_name.clone()));
+        registry.register(Connector::Ldap, move || {
+            Box::new(LdapConnector::new(ldap_pool.clone(), mapping.clone()))
+        });
+
+        if let Some(path) = &CONFIG.dummy_users_file {
+            let directory = match DummyDirectory::load(path) {
+                Ok(directory) => Arc::new(directory),
+                Err(err) => panic!("Could not load the dummy connector: {}", err),
+            };
+            log::warn!("Dummy connector enabled with users from {}. Do not use it in production.", path);
+            registry.register(Connector::Dummy, move || Box::new(DummyConnector::new(directory.clone())));
+        }
+
+        registry
+    }
+
+    /// Register the implementation of a connector, replacing any previous one
+    ///
+    /// # Arguments
+    /// * `connector` - The connector to register
+    /// * `factory` - Creates a new instance of the implementation
+    pub fn register<F>(&mut self, connector: Connector, factory: F)
+    where
+        F: Fn() -> Box<dyn Auth> + Send + Sync + 'static,
+    {
+        self.factories.insert(connector, Box::new(factory));
+    }
+
+    /// Create an instance of a connector
+    ///
+    /// # Returns
+    /// * The connector, or None if it is not enabled
+    pub fn create(&self, connector: &Connector) -> Option<Box<dyn Auth>> {
+        self.factories.get(connector).map(|factory| factory())
+    }
+}
@@ -0,0 +1,249 @@
+use crate::config::{ServerSelection, CONFIG};
+use hickory_resolver::TokioAsyncResolver;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long the servers found by an SRV lookup are used before they are looked up again
+const SRV_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// An SRV record, e.g. `_ldap._tcp.example.com. 600 IN SRV 0 100 389 dc1.example.com.`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+/// Looks up SRV records
+///
+/// `DnsSrvResolver` asks the DNS servers of the system, tests can stand in a resolver with fixed records.
+pub trait SrvResolver: Send + Sync {
+    /// Look up the SRV records of a name, e.g. `_ldap._tcp.example.com`
+    fn lookup<'a>(&'a self, name: &'a str) -> Pin<Box<dyn Future<Output = Result<Vec<SrvRecord>, String>> + Send + 'a>>;
+}
+
+/// Resolves SRV records with the DNS servers configured in `/etc/resolv.conf`
+pub struct DnsSrvResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsSrvResolver {
+    pub fn from_system_conf() -> Result<DnsSrvResolver, String> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|err| format!("Could not read the DNS configuration: {}", err))?;
+        Ok(DnsSrvResolver { resolver })
+    }
+}
+
+impl SrvResolver for DnsSrvResolver {
+    fn lookup<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SrvRecord>, String>> + Send + 'a>> {
+        Box::pin(async move {
+            let lookup = self.resolver.srv_lookup(name).await.map_err(|err| err.to_string())?;
+            Ok(lookup
+                .iter()
+                .map(|srv| SrvRecord {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: srv.target().to_utf8(),
+                })
+                .collect())
+        })
+    }
+}
+
+enum Source {
+    Urls(Vec<String>),
+    Srv {
+        name: String,
+        scheme: String,
+        resolver: Box<dyn SrvResolver>,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    /// Servers that failed, and when
+    unhealthy: HashMap<String, Instant>,
+    /// The server round robin selection starts with next
+    next: usize,
+    /// The servers found by the last SRV lookup, and when
+    discovered: Option<(Vec<String>, Instant)>,
+}
+
+/// The LDAP servers connections can be opened to
+///
+/// The servers are configured as a list of URLs, or discovered with DNS SRV records. A server a connection
+/// could not be opened to is marked unhealthy and not tried again for `retry_after`, unless all other servers
+/// fail as well.
+pub struct ServerSet {
+    source: Source,
+    selection: ServerSelection,
+    retry_after: Duration,
+    state: Mutex<State>,
+}
+
+impl ServerSet {
+    /// A fixed list of servers
+    ///
+    /// # Arguments
+    /// * `urls` - The URLs of the servers, in order of priority
+    /// * `selection` - How the server a connection is opened to is chosen
+    /// * `retry_after` - How long a server that failed is skipped
+    pub fn new(urls: Vec<String>, selection: ServerSelection, retry_after: Duration) -> ServerSet {
+        Self::with_source(Source::Urls(urls), selection, retry_after)
+    }
+
+    /// Servers discovered with the SRV records of `_ldap._tcp.<domain>`, or `_ldaps._tcp.<domain>` for LDAPS
+    ///
+    /// The servers are ordered by priority and weight. SRV weights are meant for random load balancing,
+    /// use `ServerSelection::RoundRobin` to spread the connections instead.
+    ///
+    /// # Arguments
+    /// * `domain` - The domain, e.g. `example.com`
+    /// * `ldaps` - Look up LDAPS servers instead of LDAP servers
+    /// * `resolver` - Resolves the SRV records
+    /// * `selection` - How the server a connection is opened to is chosen
+    /// * `retry_after` - How long a server that failed is skipped
+    pub fn srv(
+        domain: &str,
+        ldaps: bool,
+        resolver: Box<dyn SrvResolver>,
+        selection: ServerSelection,
+        retry_after: Duration,
+    ) -> ServerSet {
+        let scheme = if ldaps { "ldaps" } else { "ldap" };
+        let source = Source::Srv {
+            name: format!("_{}._tcp.{}", scheme, domain),
+            scheme: scheme.to_string(),
+            resolver,
+        };
+        Self::with_source(source, selection, retry_after)
+    }
+
+    /// The servers configured by `LDAP_URL` or `LDAP_SRV_DOMAIN`
+    ///
+    /// # Panics
+    /// If `LDAP_SRV_DOMAIN` is set and the DNS configuration of the system can not be read.
+    pub fn from_config() -> ServerSet {
+        let selection = CONFIG.ldap_server_selection;
+        let retry_after = Duration::from_secs(CONFIG.ldap_server_retry_seconds);
+
+        match &CONFIG.ldap_srv_domain {
+            Some(domain) => {
+                let resolver = match DnsSrvResolver::from_system_conf() {
+                    Ok(resolver) => resolver,
+                    Err(err) => panic!("LDAP_SRV_DOMAIN is set, but {}", err),
+                };
+                Self::srv(domain, CONFIG.ldap_srv_ldaps, Box::new(resolver), selection, retry_after)
+            }
+            None => Self::new(CONFIG.ldap_urls.clone(), selection, retry_after),
+        }
+    }
+
+    fn with_source(source: Source, selection: ServerSelection, retry_after: Duration) -> ServerSet {
+        ServerSet {
+            source,
+            selection,
+            retry_after,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// The servers to try when opening a connection, in order
+    ///
+    /// Healthy servers come first, ordered as configured by the `ServerSelection`. Unhealthy servers follow as a
+    /// last resort, the one that failed longest ago first.
+    pub async fn candidates(&self) -> Vec<String> {
+        let mut urls = self.urls().await;
+
+        let mut state = self.state.lock().unwrap();
+        if self.selection == ServerSelection::RoundRobin && !urls.is_empty() {
+            let start = state.next % urls.len();
+            urls.rotate_left(start);
+            state.next = state.next.wrapping_add(1);
+        }
+
+        let retry_after = self.retry_after;
+        state.unhealthy.retain(|_, failed| failed.elapsed() < retry_after);
+
+        let (mut unhealthy, healthy): (Vec<String>, Vec<String>) =
+            urls.into_iter().partition(|url| state.unhealthy.contains_key(url));
+        unhealthy.sort_by_key(|url| state.unhealthy[url]);
+
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    /// Skip a server for a while, e.g. because a connection could not be opened
+    pub fn mark_unhealthy(&self, url: &str) {
+        log::warn!("Marking LDAP server {} unhealthy for {:?}", url, self.retry_after);
+        self.state.lock().unwrap().unhealthy.insert(url.to_string(), Instant::now());
+    }
+
+    /// Use a server again, e.g. because a connection was opened
+    pub fn mark_healthy(&self, url: &str) {
+        if self.state.lock().unwrap().unhealthy.remove(url).is_some() {
+            log::info!("LDAP server {} is healthy again", url);
+        }
+    }
+
+    /// Returns `true` unless the server has been marked unhealthy within `retry_after`
+    pub fn is_healthy(&self, url: &str) -> bool {
+        match self.state.lock().unwrap().unhealthy.get(url) {
+            Some(failed) => failed.elapsed() >= self.retry_after,
+            None => true,
+        }
+    }
+
+    /// All servers, in order of priority
+    async fn urls(&self) -> Vec<String> {
+        let (name, scheme, resolver) = match &self.source {
+            Source::Urls(urls) => return urls.clone(),
+            Source::Srv { name, scheme, resolver } => (name, scheme, resolver),
+        };
+
+        let cached = self.state.lock().unwrap().discovered.clone();
+        if let Some((urls, discovered_at)) = &cached {
+            if discovered_at.elapsed() < SRV_REFRESH_INTERVAL {
+                return urls.clone();
+            }
+        }
+
+        match resolver.lookup(name).await {
+            Ok(records) => {
+                let urls = srv_urls(scheme, records);
+                log::debug!("Found LDAP servers for {}: {:?}", name, urls);
+                self.state.lock().unwrap().discovered = Some((urls.clone(), Instant::now()));
+                urls
+            }
+            Err(err) => {
+                // Keep using the servers found before, they are more likely to work than none at all
+                log::error!("SRV lookup of {} failed: {}", name, err);
+                cached.map(|(urls, _)| urls).unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// The URLs of the servers of SRV records, by ascending priority and descending weight
+fn srv_urls(scheme: &str, mut records: Vec<SrvRecord>) -> Vec<String> {
+    // A target of "." means the service is not available in the domain (RFC 2782)
+    records.retain(|record| record.target != "." && !record.target.is_empty());
+    records.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then(b.weight.cmp(&a.weight))
+            .then(a.target.cmp(&b.target))
+    });
+    records
+        .into_iter()
+        .map(|record| format!("{}://{}:{}", scheme, record.target.trim_end_matches('.'), record.port))
+        .collect()
+}
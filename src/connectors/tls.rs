@@ -0,0 +1,89 @@
+use crate::config::CONFIG;
+use ldap3::LdapConnSettings;
+use native_tls::{Certificate, Identity, TlsConnector};
+use openssl::x509::X509;
+use std::fs;
+
+/// Build the connection settings of the LDAP connections from the `LDAP_TLS_*` and `LDAP_STARTTLS` variables
+///
+/// # Returns
+/// * The settings, or a description of why the TLS configuration is invalid
+pub fn conn_settings() -> Result<LdapConnSettings, String> {
+    let client_identity = match (&CONFIG.ldap_tls_client_cert_file, &CONFIG.ldap_tls_client_key_file) {
+        (Some(cert_path), Some(key_path)) => Some((cert_path.as_str(), key_path.as_str())),
+        _ => None,
+    };
+
+    build_conn_settings(
+        CONFIG.ldap_tls_ca_file.as_deref(),
+        client_identity,
+        CONFIG.ldap_tls_verify_hostname,
+        CONFIG.ldap_starttls,
+    )
+}
+
+/// Build the connection settings of the LDAP connections
+///
+/// # Arguments
+/// * `ca_file` - A PEM encoded CA bundle trusted in addition to the system certificates
+/// * `client_identity` - The paths of the PEM encoded client certificate and its PKCS#8 key
+/// * `verify_hostname` - Whether the certificate of the server must be issued for its hostname
+/// * `starttls` - Upgrade `ldap://` connections with StartTLS
+///
+/// # Returns
+/// * The settings, or a description of why the TLS configuration is invalid
+pub fn build_conn_settings(
+    ca_file: Option<&str>,
+    client_identity: Option<(&str, &str)>,
+    verify_hostname: bool,
+    starttls: bool,
+) -> Result<LdapConnSettings, String> {
+    let mut builder = TlsConnector::builder();
+
+    if let Some(path) = ca_file {
+        for certificate in read_ca_bundle(path)? {
+            builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some((cert_path, key_path)) = client_identity {
+        let cert = fs::read(cert_path).map_err(|err| format!("Could not read {}: {}", cert_path, err))?;
+        let key = fs::read(key_path).map_err(|err| format!("Could not read {}: {}", key_path, err))?;
+        let identity = Identity::from_pkcs8(&cert, &key)
+            .map_err(|err| format!("Invalid client certificate {} or key {}: {}", cert_path, key_path, err))?;
+        builder.identity(identity);
+    }
+
+    if !verify_hostname {
+        log::warn!("LDAP server hostname verification is disabled. Do not use this in production.");
+        builder.danger_accept_invalid_hostnames(true);
+    }
+
+    let connector = builder
+        .build()
+        .map_err(|err| format!("Could not create TLS connector: {}", err))?;
+
+    Ok(LdapConnSettings::new()
+        .set_connector(connector)
+        .set_starttls(starttls))
+}
+
+/// Read every certificate of a PEM encoded CA bundle
+pub fn read_ca_bundle(path: &str) -> Result<Vec<Certificate>, String> {
+    let pem = fs::read(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+    let certificates = X509::stack_from_pem(&pem).map_err(|err| format!("Invalid CA bundle {}: {}", path, err))?;
+    if certificates.is_empty() {
+        return Err(format!("CA bundle {} contains no certificates", path));
+    }
+
+    certificates
+        .iter()
+        .map(|certificate| {
+            certificate
+                .to_der()
+                .ok()
+                .and_then(|der| Certificate::from_der(&der).ok())
+                .ok_or_else(|| format!("Invalid certificate in CA bundle {}", path))
+        })
+        .collect()
+}
@@ -0,0 +1,24 @@
+/// The longest username accepted by the connectors
+pub const MAX_USERNAME_LENGTH: usize = 256;
+
+/// Check that a username is safe to pass on to a connector
+///
+/// Usernames are escaped before they are put into a DN or search filter. This check rejects input that is
+/// never a legitimate username: empty values, control characters and absurd lengths.
+///
+/// # Arguments
+/// * `username` - The username to check
+/// # Returns
+/// * `Ok(())` if the username is acceptable, otherwise a description of the problem
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.trim().is_empty() {
+        return Err("Username must not be empty".to_string());
+    }
+    if username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(format!("Username must not be longer than {} characters", MAX_USERNAME_LENGTH));
+    }
+    if username.chars().any(char::is_control) {
+        return Err("Username must not contain control characters".to_string());
+    }
+    Ok(())
+}
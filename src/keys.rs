@@ -0,0 +1,535 @@
+use crate::config::{Config, CONFIG};
+use chrono::{DateTime, Duration, Utc};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::errors::{Error, ErrorKind, Result};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, EllipticCurveKeyType, Jwk,
+    JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use lazy_static::lazy_static;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcGroupRef, EcKey, EcPointRef};
+use openssl::error::ErrorStack;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private};
+use openssl::rand::rand_bytes;
+use openssl::rsa::Rsa;
+use openssl::sha::sha256;
+use std::fs;
+use std::sync::{Arc, RwLock};
+
+/// Key material used to sign and verify tokens
+///
+/// The HMAC algorithms (`HS256`, `HS384`, `HS512`) use the shared secret for both signing and
+/// verification. All other algorithms sign with a private key and verify with the matching public
+/// key, which means that consumers of the tokens only need access to the public key.
+///
+/// ### Arguments
+/// * `kid` - The key id, stamped into the header of every token signed with this key
+/// * `algorithm` - The algorithm used to sign tokens and the only one accepted during validation
+/// * `jwk` - The public key as a JSON Web Key. Always `None` for the HMAC algorithms, as the
+///   shared secret must never be published.
+/// * `encoding_key` - The key used to sign tokens
+/// * `decoding_key` - The key used to verify tokens
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub jwk: Option<Jwk>,
+    pub(crate) encoding_key: EncodingKey,
+    pub(crate) decoding_key: DecodingKey,
+}
+
+impl SigningKey {
+    /// Create a signing key from a shared secret
+    ///
+    /// # Arguments
+    /// * `algorithm` - One of the HMAC algorithms
+    /// * `secret` - The shared secret
+    ///
+    /// # Returns
+    /// * The signing key, or an `InvalidAlgorithm` error if `algorithm` is not an HMAC algorithm.
+    pub fn from_secret(algorithm: Algorithm, secret: &[u8]) -> Result<SigningKey> {
+        if !is_symmetric(algorithm) {
+            return Err(ErrorKind::InvalidAlgorithm.into());
+        }
+
+        // RFC 7638 thumbprint of the symmetric key, members in lexicographic order.
+        let thumbprint_input = format!(r#"{{"k":"{}","kty":"oct"}}"#, URL_SAFE_NO_PAD.encode(secret));
+
+        Ok(SigningKey {
+            kid: URL_SAFE_NO_PAD.encode(sha256(thumbprint_input.as_bytes())),
+            algorithm,
+            jwk: None,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+        })
+    }
+
+    /// Create a signing key from a PEM encoded private and public key pair
+    ///
+    /// # Arguments
+    /// * `algorithm` - One of the RSA, ECDSA or EdDSA algorithms
+    /// * `private_pem` - The PEM encoded private key, used for signing
+    /// * `public_pem` - The PEM encoded public key, used for verification
+    ///
+    /// The key id defaults to the RFC 7638 thumbprint of the public key.
+    ///
+    /// # Returns
+    /// * The signing key, or an error if the keys could not be parsed, do not fit the algorithm or the public
+    ///   key does not belong to the private key.
+    pub fn from_pem(algorithm: Algorithm, private_pem: &[u8], public_pem: &[u8]) -> Result<SigningKey> {
+        let (encoding_key, decoding_key) = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => (
+                EncodingKey::from_rsa_pem(private_pem)?,
+                DecodingKey::from_rsa_pem(public_pem)?,
+            ),
+            Algorithm::ES256 | Algorithm::ES384 => (
+                EncodingKey::from_ec_pem(private_pem)?,
+                DecodingKey::from_ec_pem(public_pem)?,
+            ),
+            Algorithm::EdDSA => (
+                EncodingKey::from_ed_pem(private_pem)?,
+                DecodingKey::from_ed_pem(public_pem)?,
+            ),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(ErrorKind::InvalidAlgorithm.into());
+            }
+        };
+
+        // Tokens signed with the private key would fail their own validation and the published JWKS
+        let invalid = |_| Error::from(ErrorKind::InvalidKeyFormat);
+        let private_key = PKey::private_key_from_pem(private_pem).map_err(invalid)?;
+        let public_key = PKey::public_key_from_pem(public_pem).map_err(invalid)?;
+        if !private_key.public_eq(&public_key) {
+            return Err(ErrorKind::InvalidKeyFormat.into());
+        }
+
+        let mut jwk = public_jwk(algorithm, public_pem)?;
+        let kid = URL_SAFE_NO_PAD.encode(sha256(thumbprint_input(&jwk.algorithm).as_bytes()));
+        jwk.common.key_id = Some(kid.clone());
+
+        Ok(SigningKey {
+            kid,
+            algorithm,
+            jwk: Some(jwk),
+            encoding_key,
+            decoding_key,
+        })
+    }
+
+    /// Generate a new random signing key
+    ///
+    /// HMAC algorithms get a 64 byte random secret. For the asymmetric algorithms a new key pair is
+    /// generated: RSA keys are 2048 bits, ECDSA keys use the curve matching the algorithm.
+    ///
+    /// # Arguments
+    /// * `algorithm` - The algorithm the key is generated for
+    pub fn generate(algorithm: Algorithm) -> Result<SigningKey> {
+        if is_symmetric(algorithm) {
+            let mut secret = [0u8; 64];
+            rand_bytes(&mut secret).map_err(|_| Error::from(ErrorKind::InvalidKeyFormat))?;
+            return SigningKey::from_secret(algorithm, &secret);
+        }
+
+        let generate_pair = || -> std::result::Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+            let key: PKey<Private> = match algorithm {
+                Algorithm::ES256 => generate_ec(Nid::X9_62_PRIME256V1)?,
+                Algorithm::ES384 => generate_ec(Nid::SECP384R1)?,
+                Algorithm::EdDSA => PKey::generate_ed25519()?,
+                _ => PKey::from_rsa(Rsa::generate(2048)?)?,
+            };
+            Ok((key.private_key_to_pem_pkcs8()?, key.public_key_to_pem()?))
+        };
+        let (private_pem, public_pem) = generate_pair().map_err(|_| Error::from(ErrorKind::InvalidKeyFormat))?;
+
+        SigningKey::from_pem(algorithm, &private_pem, &public_pem)
+    }
+
+    /// Replace the derived key id with an explicit one
+    pub fn with_kid(mut self, kid: &str) -> SigningKey {
+        self.kid = kid.to_owned();
+        if let Some(jwk) = self.jwk.as_mut() {
+            jwk.common.key_id = Some(kid.to_owned());
+        }
+        self
+    }
+
+    /// Load the signing key described by the configuration
+    ///
+    /// # Panics
+    /// Panics if the key material required by `JWT_ALGORITHM` is missing or invalid, for the same
+    /// reason as `Config::new`: the service cannot issue or validate tokens without it.
+    pub fn from_config(config: &Config) -> SigningKey {
+        let key = Self::load(config).unwrap_or_else(|err| panic!("{}", err));
+
+        match &config.jwt_key_id {
+            Some(kid) => key.with_kid(kid),
+            None => key,
+        }
+    }
+
+    /// Load the key material described by the configuration, without the `JWT_KEY_ID` override
+    fn load(config: &Config) -> std::result::Result<SigningKey, String> {
+        if is_symmetric(config.jwt_algorithm) {
+            let secret = config
+                .jwt_secret_key
+                .as_ref()
+                .ok_or("JWT_SECRET_KEY must be set when using an HMAC algorithm")?;
+
+            return SigningKey::from_secret(config.jwt_algorithm, secret.as_bytes())
+                .map_err(|_| "JWT_SECRET_KEY could not be used as signing key".to_string());
+        }
+
+        let private_key_path = config
+            .jwt_private_key_path
+            .as_ref()
+            .ok_or("JWT_PRIVATE_KEY_PATH must be set when using an asymmetric algorithm")?;
+        let public_key_path = config
+            .jwt_public_key_path
+            .as_ref()
+            .ok_or("JWT_PUBLIC_KEY_PATH must be set when using an asymmetric algorithm")?;
+
+        let private_pem =
+            fs::read(private_key_path).map_err(|_| "JWT_PRIVATE_KEY_PATH must be a readable file".to_string())?;
+        let public_pem =
+            fs::read(public_key_path).map_err(|_| "JWT_PUBLIC_KEY_PATH must be a readable file".to_string())?;
+
+        SigningKey::from_pem(config.jwt_algorithm, &private_pem, &public_pem).map_err(|_| {
+            "JWT_PRIVATE_KEY_PATH and JWT_PUBLIC_KEY_PATH must be a PEM key pair matching JWT_ALGORITHM".to_string()
+        })
+    }
+}
+
+/// Returns `true` if the algorithm signs and verifies with the same shared secret
+pub fn is_symmetric(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+/// A retired signing key, still accepted for verification until `expires_at`
+struct RetiredKey {
+    key: Arc<SigningKey>,
+    expires_at: DateTime<Utc>,
+}
+
+/// The set of keys used to sign and verify tokens
+///
+/// New tokens are always signed with the `active` key. When the key ring is rotated the previous
+/// key is retired: it is no longer used for signing, but tokens it signed are still accepted until
+/// they would have expired anyway. Keys are selected by the `kid` in the token header.
+pub struct KeyRing {
+    active: Arc<SigningKey>,
+    retired: Vec<RetiredKey>,
+}
+
+impl KeyRing {
+    pub fn new(active: SigningKey) -> KeyRing {
+        KeyRing {
+            active: Arc::new(active),
+            retired: vec![],
+        }
+    }
+
+    /// Create the key ring described by the configuration
+    ///
+    /// The secrets of `JWT_PREVIOUS_SECRET_KEYS` are retired for the lifetime of a token from now on, so the
+    /// tokens they signed before a restart are accepted until they expire.
+    ///
+    /// # Panics
+    /// Panics if the key material is missing or invalid, see `SigningKey::from_config`.
+    pub fn from_config(config: &Config) -> KeyRing {
+        let mut key_ring = KeyRing::new(SigningKey::from_config(config));
+        for secret in &config.jwt_previous_secret_keys {
+            let key = SigningKey::from_secret(config.jwt_algorithm, secret.as_bytes())
+                .unwrap_or_else(|_| panic!("JWT_PREVIOUS_SECRET_KEYS could not be used as verification keys"));
+            key_ring.retire(key, token_lifetime(config));
+        }
+        key_ring
+    }
+
+    /// The key used to sign new tokens
+    pub fn active(&self) -> Arc<SigningKey> {
+        self.active.clone()
+    }
+
+    /// Find a key that is still accepted for verification by its key id
+    pub fn find(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        if self.active.kid == kid {
+            return Some(self.active.clone());
+        }
+
+        let now = Utc::now();
+        self.retired
+            .iter()
+            .find(|retired| retired.key.kid == kid && retired.expires_at > now)
+            .map(|retired| retired.key.clone())
+    }
+
+    /// Returns `true` if a key with the same public key is active or retired
+    fn contains(&self, key: &SigningKey) -> bool {
+        let public_key = |key: &SigningKey| key.jwk.as_ref().map(|jwk| jwk.algorithm.clone());
+
+        public_key(key).is_some()
+            && std::iter::once(&self.active)
+                .chain(self.retired.iter().map(|retired| &retired.key))
+                .any(|known| public_key(known) == public_key(key))
+    }
+
+    /// Make `key` the active key and retire the current one
+    ///
+    /// # Arguments
+    /// * `key` - The new signing key
+    /// * `retire_for` - How long the previous key is still accepted for verification. This should
+    ///   be at least the maximum lifetime of a token.
+    pub fn rotate(&mut self, key: SigningKey, retire_for: Duration) {
+        self.prune();
+
+        let previous = std::mem::replace(&mut self.active, Arc::new(key));
+        self.retired.push(RetiredKey {
+            key: previous,
+            expires_at: Utc::now() + retire_for,
+        });
+    }
+
+    /// Accept `key` for verification for `retire_for`, without ever signing with it
+    pub fn retire(&mut self, key: SigningKey, retire_for: Duration) {
+        self.retired.push(RetiredKey {
+            key: Arc::new(key),
+            expires_at: Utc::now() + retire_for,
+        });
+    }
+
+    /// Drop the retired keys that are no longer accepted
+    pub fn prune(&mut self) {
+        let now = Utc::now();
+        self.retired.retain(|retired| retired.expires_at > now);
+    }
+
+    /// Returns the public keys that can be used to verify tokens as a JSON Web Key Set
+    ///
+    /// Retired keys are included until they expire. The set is empty when an HMAC algorithm is
+    /// configured.
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now();
+        let retired = self
+            .retired
+            .iter()
+            .filter(|retired| retired.expires_at > now)
+            .map(|retired| &retired.key);
+
+        JwkSet {
+            keys: std::iter::once(&self.active)
+                .chain(retired)
+                .filter_map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Returns the public keys that can be used to verify tokens as a JSON Web Key Set
+pub fn jwks() -> JwkSet {
+    KEY_RING.read().unwrap().jwks()
+}
+
+/// Rotate the global key ring
+///
+/// If the key files hold a key that has not been used before, that key becomes the new active key,
+/// so operators can roll out their own keys. Otherwise a new key is generated in memory. The
+/// previous key is retired for the lifetime of a token.
+///
+/// A shared secret is never rotated: a generated secret would only exist in this process, so other
+/// instances and every verifier holding the configured secret would reject the new tokens. Generated key
+/// pairs have the same problem for other instances, and are lost on restart along with the retired keys.
+///
+/// The key ring stays locked for writing during the whole rotation, so concurrent rotations do not retire
+/// the same key twice or drop each other's new key.
+///
+/// # Returns
+/// * The key id of the new active key, or an error if no new key could be created or an HMAC
+///   algorithm is configured.
+pub fn rotate() -> std::result::Result<String, String> {
+    if is_symmetric(CONFIG.jwt_algorithm) {
+        let algorithm = CONFIG.jwt_algorithm;
+        return Err(format!("{:?} signs with the shared JWT_SECRET_KEY, which can not be rotated", algorithm));
+    }
+    let mut key_ring = KEY_RING.write().unwrap();
+    let configured = SigningKey::load(&CONFIG)?;
+
+    let key = match key_ring.contains(&configured) {
+        false => configured,
+        true => {
+            log::warn!("The key files hold no new key, generating one that only this instance knows");
+            SigningKey::generate(CONFIG.jwt_algorithm).map_err(|err| format!("Could not generate key: {}", err))?
+        }
+    };
+    let kid = key.kid.clone();
+
+    let retired_kid = key_ring.active().kid.clone();
+    key_ring.rotate(key, token_lifetime(&CONFIG));
+    log::info!("Rotated signing key. Active key: {}, retired key: {}", kid, retired_kid);
+
+    Ok(kid)
+}
+
+/// How long a key must be accepted for verification after it signed its last token
+fn token_lifetime(config: &Config) -> Duration {
+    Duration::seconds((config.jwt_expiration_time_seconds + config.jwt_leeway_seconds) as i64)
+}
+
+/// Rotate the key ring every time the process receives `SIGHUP`
+#[cfg(unix)]
+pub async fn rotate_on_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            log::error!("Could not listen for SIGHUP, key rotation by signal is disabled: {}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        log::info!("Received SIGHUP, rotating signing key");
+        if let Err(err) = rotate() {
+            log::error!("Could not rotate signing key: {}", err);
+        }
+    }
+}
+
+/// Rotate the key ring every `interval_seconds`
+pub async fn rotate_on_schedule(interval_seconds: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_seconds));
+    // The first tick completes immediately, the configured key is used for the first interval.
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        if let Err(err) = rotate() {
+            log::error!("Could not rotate signing key: {}", err);
+        }
+    }
+}
+
+/// Convert a PEM encoded public key to a JSON Web Key
+fn public_jwk(algorithm: Algorithm, public_pem: &[u8]) -> Result<Jwk> {
+    let key = PKey::public_key_from_pem(public_pem).map_err(|_| Error::from(ErrorKind::InvalidKeyFormat))?;
+
+    let parameters = match key.id() {
+        Id::RSA => {
+            let rsa = key.rsa().map_err(|_| Error::from(ErrorKind::InvalidRsaKey(String::new())))?;
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+                e: URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+            })
+        }
+        Id::EC => {
+            let ec = key.ec_key().map_err(|_| Error::from(ErrorKind::InvalidEcdsaKey))?;
+            let (curve, x, y) = ec_coordinates(ec.group(), ec.public_key())?;
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve,
+                x,
+                y,
+            })
+        }
+        Id::ED25519 => {
+            let raw = key.raw_public_key().map_err(|_| Error::from(ErrorKind::InvalidKeyFormat))?;
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(raw),
+            })
+        }
+        _ => return Err(ErrorKind::InvalidKeyFormat.into()),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm(algorithm)),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// Extract the curve and the padded, base64url encoded affine coordinates of an EC public key
+fn ec_coordinates(group: &EcGroupRef, point: &EcPointRef) -> Result<(EllipticCurve, String, String)> {
+    let (curve, size) = match group.curve_name() {
+        Some(Nid::X9_62_PRIME256V1) => (EllipticCurve::P256, 32),
+        Some(Nid::SECP384R1) => (EllipticCurve::P384, 48),
+        _ => return Err(ErrorKind::InvalidEcdsaKey.into()),
+    };
+
+    let coordinates = || -> std::result::Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+        let mut ctx = BigNumContext::new()?;
+        let mut x = BigNum::new()?;
+        let mut y = BigNum::new()?;
+        point.affine_coordinates(group, &mut x, &mut y, &mut ctx)?;
+        Ok((x.to_vec_padded(size)?, y.to_vec_padded(size)?))
+    };
+    let (x, y) = coordinates().map_err(|_| Error::from(ErrorKind::InvalidEcdsaKey))?;
+
+    Ok((curve, URL_SAFE_NO_PAD.encode(x), URL_SAFE_NO_PAD.encode(y)))
+}
+
+fn generate_ec(curve: Nid) -> std::result::Result<PKey<Private>, ErrorStack> {
+    let group = EcGroup::from_curve_name(curve)?;
+    PKey::from_ec_key(EcKey::generate(&group)?)
+}
+
+/// The required members of a public key in lexicographic order, as specified by RFC 7638
+fn thumbprint_input(parameters: &AlgorithmParameters) -> String {
+    match parameters {
+        AlgorithmParameters::RSA(rsa) => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, rsa.e, rsa.n),
+        AlgorithmParameters::EllipticCurve(ec) => format!(
+            r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+            curve_name(&ec.curve),
+            ec.x,
+            ec.y
+        ),
+        AlgorithmParameters::OctetKeyPair(okp) => {
+            format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, curve_name(&okp.curve), okp.x)
+        }
+        AlgorithmParameters::OctetKey(oct) => format!(r#"{{"k":"{}","kty":"oct"}}"#, oct.value),
+    }
+}
+
+fn curve_name(curve: &EllipticCurve) -> &'static str {
+    match curve {
+        EllipticCurve::P256 => "P-256",
+        EllipticCurve::P384 => "P-384",
+        EllipticCurve::P521 => "P-521",
+        EllipticCurve::Ed25519 => "Ed25519",
+    }
+}
+
+fn key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::HS256 => KeyAlgorithm::HS256,
+        Algorithm::HS384 => KeyAlgorithm::HS384,
+        Algorithm::HS512 => KeyAlgorithm::HS512,
+        Algorithm::ES256 => KeyAlgorithm::ES256,
+        Algorithm::ES384 => KeyAlgorithm::ES384,
+        Algorithm::RS256 => KeyAlgorithm::RS256,
+        Algorithm::RS384 => KeyAlgorithm::RS384,
+        Algorithm::RS512 => KeyAlgorithm::RS512,
+        Algorithm::PS256 => KeyAlgorithm::PS256,
+        Algorithm::PS384 => KeyAlgorithm::PS384,
+        Algorithm::PS512 => KeyAlgorithm::PS512,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+    }
+}
+
+// The key ring starts out with the configured key, and is only replaced through rotation.
+lazy_static! {
+    pub static ref KEY_RING: RwLock<KeyRing> = RwLock::new(KeyRing::from_config(&CONFIG));
+}
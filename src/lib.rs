@@ -5,3 +5,5 @@ pub mod connectors;
 
 pub mod models;
 pub mod config;
+pub mod keys;
+pub mod stores;
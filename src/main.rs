@@ -1,14 +1,25 @@
+use actix_web::http::header::HeaderValue;
 use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
-use authio::models::AuthRequest;
-use authio::config::CONFIG;
+use actix_web::error::{InternalError, JsonPayloadError};
+use authio::models::{
+    Access, AuthError, AuthOutcome, AuthRequest, ErrorCode, ErrorResponse, IntrospectionRequest,
+    IntrospectionResponse, LogoutRequest, PermissionQuery, RefreshRequest, ResolvedPermissions, RevokeRequest,
+    TokenResponse,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use authio::config::{PermissionFailurePolicy, CONFIG};
+use authio::keys;
 use authio::models::jwt::{validate_token, JWTClaim};
 use authio::models::jwt;
-use authio::traits::Authenticate;
-use authio::traits::Authorize;
-use authio::connectors::{ldap};
+use authio::connectors::username::validate_username;
+use authio::connectors::pool::{self, LdapPool};
+use authio::connectors::{Connector, ConnectorRegistry};
+use authio::stores::refresh_tokens::{RefreshError, RefreshToken};
+use authio::stores::{Denylist, RefreshTokenStore};
 use jsonwebtoken::errors::{Error, ErrorKind};
 use jsonwebtoken::TokenData;
-use log;
+use openssl::memcmp;
 
 #[cfg(test)]
 pub mod tests;
@@ -20,52 +31,314 @@ pub mod tests;
 ///
 /// # Steps
 ///
-/// 1. An instance of `LdapAuthenticate` is created with a username and password.
-/// 2. The `bind` method of the `LdapAuthenticate` instance is called to authenticate the user against the LDAP server.
-/// 3. A JWT token is created for the authenticated user.
-/// 4. If the token creation is successful, the token is returned in the response body with an HTTP status of 200.
-/// 5. If there is an error during token creation, an error message is logged and an HTTP status of 500 is
-/// returned with a generic error message.
+/// 1. The connector selected by the request is created from the `ConnectorRegistry`, e.g. an `LdapConnector`.
+/// 2. The `authenticate` method of the connector is called to authenticate the user against its backend.
+/// 3. A JWT token is created for the authenticated user, with the permissions resolved by the connector. If they
+///    could not be resolved completely, or not at all because the backend failed after the user authenticated,
+///    `PERMISSION_FAILURE_POLICY` decides whether a token is issued.
+/// 4. If the token creation is successful, a `TokenResponse` is returned with an HTTP status of 200. It contains
+///    the token, its lifetime, the resolved permissions and a refresh token starting a new token family.
+/// 5. If anything fails, an `ErrorResponse` with a stable error code is returned.
 #[post("/login")]
-async fn create_token(auth: web::Json<AuthRequest>) -> impl Responder {
+async fn create_token(
+    auth: web::Json<AuthRequest>,
+    refresh_tokens: web::Data<RefreshTokenStore>,
+    connectors: web::Data<ConnectorRegistry>,
+    policy: web::Data<PermissionFailurePolicy>,
+) -> impl Responder {
     // Extract the username and password from the request
     let username = &auth.username;
     let password = &auth.password;
 
-    // Create a new LDAPAuth struct with the username and password from the request
-    let mut ldap = ldap::LdapConnector::new();
-
-    // Initialize the LDAP connection
-    if !ldap.initialize().await {
-        return HttpResponse::InternalServerError().body("We seem to have some troubles with \
-        our authentication services. Please try again later.");
+    if let Err(err) = validate_username(username) {
+        return error_response(ErrorCode::InvalidRequest, &err);
     }
-    let create_token = ldap.authenticate(username, password).await;
 
-    // If the user is not authenticated, return an Unauthorized response
-    if !create_token {
-        // Unbind the LDAP connection, we are done with it
-        ldap.unbind_ldap().await;
-        return HttpResponse::Unauthorized().body("Invalid credentials");
+    // Create the connector the request asks for
+    let mut connector = match connectors.create(&auth.connector) {
+        Some(connector) => connector,
+        None => return error_response(ErrorCode::InvalidRequest, "The requested connector is not enabled"),
+    };
+
+    // Connect to the backend of the connector
+    if let Err(err) = connector.connect().await {
+        return auth_error_response(&err);
     }
 
+    let principal = match connector.authenticate(username, password).await {
+        Ok(AuthOutcome::Authenticated(principal)) => principal,
+        // If the user is not authenticated, tell them why as far as the connector knows
+        Ok(AuthOutcome::Denied(failure)) => {
+            // Disconnect from the backend, we are done with it
+            connector.disconnect().await;
+            return error_response(failure.error_code(), failure.description());
+        }
+        Err(err) => {
+            connector.disconnect().await;
+            return auth_error_response(&err);
+        }
+    };
+    log::debug!("Authenticated {:?}", principal);
+
     // If the user is authenticated, lookup the user's permissions
-    let permissions = ldap.resolve_permission(&username).await;
+    let resolved = match connector.resolve_permission(&principal.username).await {
+        // The user has proven who they are, only their permissions are unknown
+        Err(AuthError::BackendUnavailable(err)) => Ok(ResolvedPermissions::partial(vec![], &err)),
+        resolved => resolved,
+    };
 
-    // Unbind the LDAP connection, we are done with it
-    ldap.unbind_ldap().await;
+    // Disconnect from the backend, we are done with it
+    connector.disconnect().await;
+
+    let resolved = match apply_permission_policy(**policy, &principal.username, resolved) {
+        Ok(resolved) => resolved,
+        Err(err) => return auth_error_response(&err),
+    };
+    let degraded = !resolved.is_complete();
 
     // Create a JWT token for the user
-    let token = match jwt::issue_token(&auth.username, permissions) {
+    let token = match jwt::issue_token(&principal.username, resolved.permissions.clone(), degraded) {
         Ok(token) => token,
         Err(err) => {
             log::error!("Could not issue token: {}", err);
-            return HttpResponse::InternalServerError()
-                .body("Something went wrong. Please try again later.");
+            return error_response(ErrorCode::ServerError, "Something went wrong. Please try again later.");
         }
     };
 
-    return HttpResponse::Ok().body(token);
+    let refresh_token = issue_refresh_token(&refresh_tokens, &principal.username, auth.connector.clone(), None);
+
+    HttpResponse::Ok().json(TokenResponse::bearer(
+        token,
+        CONFIG.jwt_expiration_time_seconds,
+        refresh_token,
+        resolved.permissions,
+        degraded,
+    ))
+}
+
+/// Endpoint to exchange a refresh token for a new access token
+///
+/// This function is mapped to the "/token/refresh" route.
+///
+/// # Steps
+///
+/// 1. The refresh token is redeemed. A refresh token can only be redeemed once, presenting it a second time
+///    revokes every refresh token issued since the login it originates from.
+/// 2. The permissions of the user are resolved again with the connector the user logged in with, so changes in
+///    group membership are picked up. If the user no longer exists, or their account has been disabled or
+///    locked, the token family is revoked.
+/// 3. A `TokenResponse` is returned with the new access token and a new refresh token of the same family.
+#[post("/token/refresh")]
+async fn refresh_access_token(
+    request: web::Json<RefreshRequest>,
+    refresh_tokens: web::Data<RefreshTokenStore>,
+    connectors: web::Data<ConnectorRegistry>,
+    policy: web::Data<PermissionFailurePolicy>,
+) -> HttpResponse {
+    let redeemed = match refresh_tokens.redeem(&request.refresh_token) {
+        Ok(redeemed) => redeemed,
+        Err(RefreshError::Reused) => {
+            return error_response(ErrorCode::RefreshTokenReused, "Refresh token has already been used")
+        }
+        Err(RefreshError::Invalid) => return error_response(ErrorCode::InvalidGrant, "Invalid refresh token"),
+    };
+
+    let resolved = match resolve_permissions(&connectors, &redeemed.connector, &redeemed.subject, **policy).await {
+        Ok(resolved) => resolved,
+        Err(err @ (AuthError::UnknownUser(_) | AuthError::Denied(_))) => {
+            // The account has been deleted or disabled since the login, it must not get new tokens
+            log::warn!("Revoking the refresh tokens of {}: {}", redeemed.subject, err);
+            refresh_tokens.revoke(&request.refresh_token);
+            return error_response(ErrorCode::InvalidGrant, "Invalid refresh token");
+        }
+        Err(err) => {
+            // The client did not get a new token, so it must be able to retry with the same one
+            refresh_tokens.release(&request.refresh_token);
+            return auth_error_response(&err);
+        }
+    };
+
+    let degraded = !resolved.is_complete();
+    let token = match jwt::issue_token(&redeemed.subject, resolved.permissions.clone(), degraded) {
+        Ok(token) => token,
+        Err(err) => {
+            log::error!("Could not issue token: {}", err);
+            refresh_tokens.release(&request.refresh_token);
+            return error_response(ErrorCode::ServerError, "Something went wrong. Please try again later.");
+        }
+    };
+
+    let refresh_token =
+        issue_refresh_token(&refresh_tokens, &redeemed.subject, redeemed.connector.clone(), Some(&redeemed));
+
+    HttpResponse::Ok().json(TokenResponse::bearer(
+        token,
+        CONFIG.jwt_expiration_time_seconds,
+        refresh_token,
+        resolved.permissions,
+        degraded,
+    ))
+}
+
+/// Issues a refresh token, unless refresh tokens are disabled.
+///
+/// Refresh tokens are disabled by setting `REFRESH_TOKEN_EXPIRATION_TIME_SECONDS` to 0.
+///
+/// # Arguments
+///
+/// * `refresh_tokens` - The store to issue the refresh token from.
+/// * `subject` - The user the refresh token is issued to.
+/// * `connector` - The connector the user authenticated with.
+/// * `rotated` - The redeemed token whose family is continued, None to start a new family.
+///
+/// # Returns
+///
+/// * `Option<String>` - The refresh token, None if refresh tokens are disabled.
+fn issue_refresh_token(
+    refresh_tokens: &RefreshTokenStore,
+    subject: &str,
+    connector: Connector,
+    rotated: Option<&RefreshToken>,
+) -> Option<String> {
+    if CONFIG.refresh_token_expiration_time_seconds == 0 {
+        return None;
+    }
+    Some(refresh_tokens.issue(subject, connector, rotated))
+}
+
+/// Builds the response for an error of the token endpoints.
+///
+/// # Arguments
+///
+/// * `code` - The error code, which also determines the HTTP status.
+/// * `description` - A human readable description of the error.
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response with an `ErrorResponse` as JSON body.
+fn error_response(code: ErrorCode, description: &str) -> HttpResponse {
+    let mut response = match code {
+        ErrorCode::InvalidRequest => HttpResponse::BadRequest(),
+        ErrorCode::InvalidCredentials | ErrorCode::InvalidGrant | ErrorCode::RefreshTokenReused => {
+            HttpResponse::Unauthorized()
+        }
+        // The credentials are known, but the account can not be used
+        ErrorCode::AccountLocked
+        | ErrorCode::AccountDisabled
+        | ErrorCode::AccountExpired
+        | ErrorCode::PasswordExpired
+        | ErrorCode::PasswordChangeRequired
+        | ErrorCode::LogonRestricted => HttpResponse::Forbidden(),
+        ErrorCode::MfaRequired => HttpResponse::Unauthorized(),
+        ErrorCode::TemporarilyUnavailable => HttpResponse::ServiceUnavailable(),
+        ErrorCode::ServerError => HttpResponse::InternalServerError(),
+    };
+    response.json(ErrorResponse::new(code, description))
+}
+
+/// Builds the response for a connector that could not answer.
+///
+/// # Arguments
+///
+/// * `err` - The error of the connector.
+///
+/// # Returns
+///
+/// * `HttpResponse` - 503 if the backend is unavailable, 401 if the user does not exist, 500 otherwise. The details
+///   are only logged.
+fn auth_error_response(err: &AuthError) -> HttpResponse {
+    log::error!("Connector failed: {}", err);
+    match err {
+        AuthError::BackendUnavailable(_) => error_response(
+            ErrorCode::TemporarilyUnavailable,
+            "We seem to have some troubles with our authentication services. Please try again later.",
+        ),
+        AuthError::UnknownUser(_) => error_response(ErrorCode::InvalidCredentials, "Invalid credentials"),
+        AuthError::Denied(failure) => error_response(failure.error_code(), failure.description()),
+        AuthError::Internal(_) => {
+            error_response(ErrorCode::ServerError, "Something went wrong. Please try again later.")
+        }
+    }
+}
+
+/// Turns a JSON body that could not be deserialized into an `ErrorResponse`.
+fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = error_response(ErrorCode::InvalidRequest, &err.to_string());
+    InternalError::from_response(err, response).into()
+}
+
+/// Resolves the permissions of a user without authenticating them.
+///
+/// # Arguments
+///
+/// * `connectors` - The registry to create the connector from.
+/// * `connector` - The connector the user authenticated with.
+/// * `username` - The user to resolve the permissions for.
+/// * `policy` - The configured `PERMISSION_FAILURE_POLICY`.
+///
+/// # Returns
+///
+/// * `Result<ResolvedPermissions, AuthError>` - The permissions of the user after applying the policy, an
+///   `AuthError` if the connector is not enabled or its backend could not answer.
+async fn resolve_permissions(
+    connectors: &ConnectorRegistry,
+    connector: &Connector,
+    username: &str,
+    policy: PermissionFailurePolicy,
+) -> Result<ResolvedPermissions, AuthError> {
+    let mut connector = match connectors.create(connector) {
+        Some(created) => created,
+        None => return Err(AuthError::BackendUnavailable(format!("Connector {:?} is not enabled", connector))),
+    };
+
+    connector.connect().await?;
+    let resolved = connector.resolve_permission(username).await;
+    connector.disconnect().await;
+    apply_permission_policy(policy, username, resolved)
+}
+
+/// Decides whether a token is issued with permissions that could not be resolved completely.
+///
+/// The policy only applies to partial permissions, which a connector returns when it found the user, but not
+/// all of their groups. On login, a backend that fails after the user authenticated yields partial permissions
+/// without any permission. Errors, e.g. a backend that could not be asked at all, are returned as they are, so
+/// no token is ever issued without asking the backend.
+///
+/// # Arguments
+///
+/// * `policy` - The configured `PERMISSION_FAILURE_POLICY`.
+/// * `username` - The user the permissions were resolved for.
+/// * `resolved` - The permissions resolved by the connector, or why they could not be resolved.
+///
+/// # Returns
+///
+/// * `Result<ResolvedPermissions, AuthError>` - The permissions to issue the token with, partial if the token
+///   must be marked as degraded. An `AuthError` if no token must be issued.
+fn apply_permission_policy(
+    policy: PermissionFailurePolicy,
+    username: &str,
+    resolved: Result<ResolvedPermissions, AuthError>,
+) -> Result<ResolvedPermissions, AuthError> {
+    let resolved = resolved?;
+    let failure = match &resolved.failure {
+        Some(failure) => failure.clone(),
+        None => return Ok(resolved),
+    };
+
+    match policy {
+        PermissionFailurePolicy::Fail => Err(AuthError::BackendUnavailable(format!(
+            "The permissions of {} could not be resolved: {}",
+            username, failure
+        ))),
+        PermissionFailurePolicy::Degraded => {
+            log::warn!("Issuing a degraded token to {}: {}", username, failure);
+            Ok(resolved)
+        }
+        PermissionFailurePolicy::Proceed => {
+            log::warn!("The permissions of {} may be incomplete: {}", username, failure);
+            Ok(ResolvedPermissions::complete(resolved.permissions))
+        }
+    }
 }
 
 /// Endpoint to validate a JWT token
@@ -74,23 +347,50 @@ async fn create_token(auth: web::Json<AuthRequest>) -> impl Responder {
 /// returns an `HttpResponse`.
 /// This function is mapped to the "/validate_request" route.
 ///
+/// The endpoint can be used as forward auth target of a reverse proxy (nginx `auth_request`, Traefik
+/// `ForwardAuth`, Caddy `forward_auth`). A successful response carries the identity of the user in the
+/// configured headers, e.g. `X-Auth-User`, `X-Auth-Company` and `X-Auth-Permissions`, which the proxy can
+/// pass on to the upstream application.
+///
+/// Optionally the token must grant a permission, given as query parameters
+/// (`/validate_request?permission=tool1&access=WRITE`) or as `X-Required-Permission` and `X-Required-Access`
+/// headers. If the permission is not granted, an `HttpResponse::Forbidden` is returned.
+///
 /// # Steps
 ///
 /// 1. The JWT token is extracted from the request's Authorization header using the `extract_token` function.
 /// 2. If a token is found, it is validated using the `validate_token` function.
-/// 3. The result of the token validation is handled by the `handle_validation_result` function,
-/// which returns an appropriate `HttpResponse`.
-/// 4. If no token is found, an `HttpResponse::Unauthorized` is returned with a body of "No authorization header found".
+/// 3. If the token is valid but has been revoked, an `HttpResponse::Unauthorized` is returned.
+/// 4. If the token is valid but does not grant the required permission, an `HttpResponse::Forbidden` is returned.
+/// 5. The result of the token validation is handled by the `handle_validation_result` function,
+///    which returns an appropriate `HttpResponse`.
+/// 6. If no token is found, an `HttpResponse::Unauthorized` is returned with a body of "No authorization header found".
 ///
 /// # Arguments
 ///
 /// * `req` - The HttpRequest from which the token is to be extracted and validated.
+/// * `query` - The permission the token must grant, if any.
+/// * `denylist` - The revoked tokens.
 ///
 /// # Returns
 ///
 /// * `HttpResponse` - The appropriate HttpResponse based on the token extraction and validation result.
 #[get("/validate_request")]
-async fn validate_request(req: HttpRequest) -> HttpResponse {
+async fn validate_request(
+    req: HttpRequest,
+    query: web::Query<PermissionQuery>,
+    denylist: web::Data<Denylist>,
+) -> HttpResponse {
+    // The required permission is taken from the query, or from the headers set by a proxy
+    let permission = query.permission.clone().or_else(|| header_value(&req, "X-Required-Permission"));
+    let access = match query.access.clone().or_else(|| header_value(&req, "X-Required-Access")) {
+        Some(access) => match Access::from_str(&access) {
+            Ok(access) => Some(access),
+            Err(err) => return HttpResponse::BadRequest().body(err),
+        },
+        None => None,
+    };
+
     // Extract the token from the request
     let token = extract_token(req).await;
     match token {
@@ -98,6 +398,20 @@ async fn validate_request(req: HttpRequest) -> HttpResponse {
         Some(token_str) => {
             let validation_result: Result<TokenData<JWTClaim>, Error> =
                 validate_token(token_str).await;
+
+            // A valid token can still have been revoked before it expired
+            if let Ok(token_data) = &validation_result {
+                if denylist.is_revoked(&token_data.claims) {
+                    return HttpResponse::Unauthorized().body("Token has been revoked");
+                }
+
+                if let Some(permission) = &permission {
+                    if !token_data.claims.grants(permission, access.as_ref()) {
+                        return HttpResponse::Forbidden().body("Missing permission");
+                    }
+                }
+            }
+
             handle_validation_result(validation_result).await
         }
 
@@ -106,6 +420,147 @@ async fn validate_request(req: HttpRequest) -> HttpResponse {
     }
 }
 
+/// Endpoint to introspect a token, as defined by RFC 7662
+///
+/// Accepts the token as form parameter and returns its claims as JSON, so API gateways that speak
+/// introspection natively can use the service. Expired, invalid and revoked tokens are reported
+/// as `{"active": false}`.
+/// This function is mapped to the "/introspect" route. The introspection API key must be sent as
+/// bearer token, without one the endpoint is disabled (RFC 7662, section 2.1).
+#[post("/introspect")]
+async fn introspect(
+    req: HttpRequest,
+    form: web::Form<IntrospectionRequest>,
+    denylist: web::Data<Denylist>,
+) -> HttpResponse {
+    let api_key = match &CONFIG.introspection_api_key {
+        Some(api_key) => api_key,
+        None => return HttpResponse::Unauthorized().body("Introspection is disabled"),
+    };
+    if let Some(response) = authorize_api_key(&req, api_key).await {
+        return response;
+    }
+
+    let response = match validate_token(form.token.clone()).await {
+        Ok(token_data) if !denylist.is_revoked(&token_data.claims) => IntrospectionResponse::from(token_data.claims),
+        Ok(_) => IntrospectionResponse::inactive(),
+        Err(err) => {
+            log::debug!("Introspected token is not active: {}", err);
+            IntrospectionResponse::inactive()
+        }
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+/// Endpoint to log out
+///
+/// Revokes the access token from the Authorization header, so it is no longer accepted by
+/// `/validate_request` even though it has not expired yet. If the body contains a refresh token,
+/// it is revoked together with every other refresh token of the same login.
+/// This function is mapped to the "/logout" route.
+#[post("/logout")]
+async fn logout(
+    req: HttpRequest,
+    body: Option<web::Json<LogoutRequest>>,
+    denylist: web::Data<Denylist>,
+    refresh_tokens: web::Data<RefreshTokenStore>,
+) -> HttpResponse {
+    let token = match extract_token(req).await {
+        Some(token) => token,
+        None => return HttpResponse::Unauthorized().body("Missing authorization header"),
+    };
+
+    let token_data = match validate_token(token).await {
+        Ok(token_data) => token_data,
+        Err(err) => return handle_validation_result(Err(err)).await,
+    };
+
+    denylist.revoke(&token_data.claims);
+
+    if let Some(refresh_token) = body.as_ref().and_then(|body| body.refresh_token.as_ref()) {
+        refresh_tokens.revoke(refresh_token);
+    }
+
+    HttpResponse::Ok().body("Logged out")
+}
+
+/// Endpoint to revoke tokens
+///
+/// Revokes a single access token, or every access and refresh token of a subject, e.g. when a user
+/// is offboarded. Revoked access tokens are rejected by `/validate_request` until they expire.
+/// This function is mapped to the "/admin/revoke" route and requires the admin API key.
+#[post("/admin/revoke")]
+async fn revoke(
+    req: HttpRequest,
+    body: web::Json<RevokeRequest>,
+    denylist: web::Data<Denylist>,
+    refresh_tokens: web::Data<RefreshTokenStore>,
+) -> HttpResponse {
+    if let Some(response) = authorize_admin(&req).await {
+        return response;
+    }
+
+    if body.token.is_none() && body.sub.is_none() {
+        return HttpResponse::BadRequest().body("Either token or sub must be set");
+    }
+
+    if let Some(token) = &body.token {
+        match validate_token(token.clone()).await {
+            Ok(token_data) => denylist.revoke(&token_data.claims),
+            Err(err) => return handle_validation_result(Err(err)).await,
+        }
+    }
+
+    if let Some(subject) = &body.sub {
+        denylist.revoke_subject(subject);
+        refresh_tokens.revoke_subject(subject);
+    }
+
+    HttpResponse::Ok().body("Revoked")
+}
+
+/// Endpoint to publish the public keys used to verify tokens
+///
+/// Returns the keys as a JSON Web Key Set (RFC 7517), so consumers can fetch the verification keys
+/// dynamically and select one by the `kid` in the token header.
+/// This function is mapped to the "/.well-known/jwks.json" route.
+///
+/// The set is empty when tokens are signed with a shared secret (HS256, HS384, HS512).
+#[get("/.well-known/jwks.json")]
+async fn jwks() -> HttpResponse {
+    HttpResponse::Ok().json(keys::jwks())
+}
+
+/// Endpoint to rotate the signing key
+///
+/// The current signing key is retired and stays valid for verification until all tokens signed with
+/// it have expired. New tokens are signed with the new key.
+/// This function is mapped to the "/admin/keys/rotate" route and requires the admin API key.
+///
+/// # Returns
+///
+/// * `HttpResponse` - The key id of the new signing key on success. `HttpResponse::Conflict` if tokens are
+///   signed with a shared secret, which can not be rotated.
+#[post("/admin/keys/rotate")]
+async fn rotate_keys(req: HttpRequest) -> HttpResponse {
+    if let Some(response) = authorize_admin(&req).await {
+        return response;
+    }
+
+    if keys::is_symmetric(CONFIG.jwt_algorithm) {
+        return HttpResponse::Conflict().body("The shared secret can not be rotated");
+    }
+
+    match keys::rotate() {
+        Ok(kid) => HttpResponse::Ok().body(kid),
+        Err(err) => {
+            log::error!("Could not rotate signing key: {}", err);
+            HttpResponse::InternalServerError().body("Could not rotate signing key")
+        }
+    }
+}
+
 #[get("/")]
 async fn ping() -> impl Responder {
     HttpResponse::Ok().body("OK")
@@ -133,6 +588,58 @@ async fn extract_token(req: HttpRequest) -> Option<String> {
     }
 }
 
+/// Reads a header of the request as string.
+///
+/// # Returns
+///
+/// * `Option<String>` - The value of the header if it is set and valid, None otherwise.
+fn header_value(req: &HttpRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// Checks that the request carries the admin API key as bearer token.
+///
+/// # Arguments
+///
+/// * `req` - The HttpRequest to authorize.
+///
+/// # Returns
+///
+/// * `Option<HttpResponse>` - None if the request is authorized, the error response otherwise.
+async fn authorize_admin(req: &HttpRequest) -> Option<HttpResponse> {
+    match &CONFIG.admin_api_key {
+        Some(admin_api_key) => authorize_api_key(req, admin_api_key).await,
+        None => Some(HttpResponse::Forbidden().body("Admin API is disabled")),
+    }
+}
+
+/// Checks that the request carries the given API key as bearer token.
+///
+/// # Arguments
+///
+/// * `req` - The HttpRequest to authorize.
+/// * `api_key` - The expected API key.
+///
+/// # Returns
+///
+/// * `Option<HttpResponse>` - None if the request is authorized, the error response otherwise.
+async fn authorize_api_key(req: &HttpRequest, api_key: &str) -> Option<HttpResponse> {
+    let token = match extract_token(req.clone()).await {
+        Some(token) => token,
+        None => return Some(HttpResponse::Unauthorized().body("Missing authorization header")),
+    };
+
+    // Compare in constant time to not leak the key through response timings
+    if token.len() == api_key.len() && memcmp::eq(token.as_bytes(), api_key.as_bytes()) {
+        None
+    } else {
+        Some(HttpResponse::Unauthorized().body("Invalid API key"))
+    }
+}
+
 /// Handles the HttpResponse based on the result of the token validation.
 ///
 /// # Arguments
@@ -146,11 +653,27 @@ async fn handle_validation_result(
     validation_result: Result<TokenData<JWTClaim>, Error>,
 ) -> HttpResponse {
     match validation_result {
-        Ok(_) => HttpResponse::Ok().body("Token valid"),
+        Ok(token_data) => {
+            let mut response = HttpResponse::Ok();
+            for (name, value) in token_data.claims.forward_auth_headers() {
+                // Leaving out a header would let the proxy pass the request on without the identity
+                match HeaderValue::from_str(&value) {
+                    Ok(value) => {
+                        response.insert_header((name, value));
+                    }
+                    Err(_) => {
+                        log::error!("Could not set forward auth header {}: invalid value {:?}", name, value);
+                        return HttpResponse::InternalServerError().body("Identity can not be sent in headers");
+                    }
+                }
+            }
+            response.body("Token valid")
+        }
         Err(err) => match *err.kind() {
             ErrorKind::InvalidToken => HttpResponse::Unauthorized().body("Invalid token"),
             ErrorKind::InvalidKeyFormat => HttpResponse::Unauthorized().body("Invalid key format"),
             ErrorKind::ExpiredSignature => HttpResponse::Unauthorized().body("Token has expired"),
+            ErrorKind::ImmatureSignature => HttpResponse::Unauthorized().body("Token is not valid yet"),
             ErrorKind::InvalidIssuer => HttpResponse::Unauthorized().body("Invalid issuer"),
             ErrorKind::InvalidSubject => HttpResponse::Unauthorized().body("Invalid subject"),
             ErrorKind::InvalidAudience => HttpResponse::Unauthorized().body("Invalid audience"),
@@ -164,8 +687,46 @@ async fn handle_validation_result(
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
-    HttpServer::new(|| App::new().service(create_token).service(validate_request))
-        .bind((CONFIG.http_bind_address.to_string(), CONFIG.http_port))?
-        .run()
-        .await
+
+    #[cfg(unix)]
+    actix_web::rt::spawn(keys::rotate_on_signal());
+    if let Some(interval) = CONFIG.jwt_key_rotation_interval_seconds {
+        actix_web::rt::spawn(keys::rotate_on_schedule(interval));
+    }
+
+    let refresh_tokens = web::Data::new(RefreshTokenStore::new(CONFIG.refresh_token_expiration_time_seconds));
+    let denylist = web::Data::new(Denylist::new(CONFIG.jwt_expiration_time_seconds, CONFIG.jwt_leeway_seconds));
+
+    // Connections to the LDAP server are shared across requests, the pool lives as long as the registry
+    let ldap_pool = Arc::new(LdapPool::from_config());
+    if CONFIG.refresh_token_expiration_time_seconds > 0 && CONFIG.ldap_bind_dn.is_none() {
+        // The user does not bind on refresh, so they are looked up as the service identity
+        log::warn!("LDAP_BIND_DN is not set, refreshing tokens of LDAP users fails on most directories");
+    }
+    actix_web::rt::spawn(pool::maintain_on_schedule(
+        ldap_pool.clone(),
+        CONFIG.ldap_pool_health_check_interval_seconds,
+    ));
+    let connectors = web::Data::new(ConnectorRegistry::from_config(ldap_pool));
+    let permission_failure_policy = web::Data::new(CONFIG.permission_failure_policy);
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(refresh_tokens.clone())
+            .app_data(denylist.clone())
+            .app_data(connectors.clone())
+            .app_data(permission_failure_policy.clone())
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .service(create_token)
+            .service(refresh_access_token)
+            .service(validate_request)
+            .service(introspect)
+            .service(logout)
+            .service(jwks)
+            .service(rotate_keys)
+            .service(revoke)
+    })
+    .bind((CONFIG.http_bind_address.to_string(), CONFIG.http_port))?
+    .run()
+    .await
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 /// The `AccessKind` enum represents the different types of access an entity can have in the system.
 ///
@@ -6,9 +7,23 @@ use serde::{Deserialize, Serialize};
 /// * `READ`: The user can read data.
 /// * `WRITE`: The user can write or modify data.
 /// * `EXECUTE`: The user can execute certain actions.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Access {
     READ,
     WRITE,
     EXECUTE,
 }
+
+impl FromStr for Access {
+    type Err = String;
+
+    /// Parse an access type, case insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "READ" => Ok(Access::READ),
+            "WRITE" => Ok(Access::WRITE),
+            "EXECUTE" => Ok(Access::EXECUTE),
+            _ => Err(format!("Unknown access type: {}", s)),
+        }
+    }
+}
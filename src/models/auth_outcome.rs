@@ -0,0 +1,122 @@
+use crate::models::ErrorCode;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The answer of a backend to an authentication attempt
+///
+/// A backend that could not give an answer, e.g. because it can not be reached, is reported as `AuthError`
+/// instead, so it is never mistaken for a refused login.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthOutcome {
+    /// The user is authenticated
+    Authenticated(Principal),
+    /// The user is not authenticated, for the given reason
+    Denied(AuthFailure),
+}
+
+/// An authenticated user
+///
+/// ### Arguments
+/// * `username` - The username the user authenticated with
+/// * `attributes` - Attributes of the user known to the backend, e.g. `dn` for LDAP
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    pub username: String,
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+impl Principal {
+    /// A principal without attributes
+    pub fn new(username: &str) -> Principal {
+        Principal {
+            username: username.to_string(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Add an attribute
+    pub fn with_attribute(mut self, name: &str, value: &str) -> Principal {
+        self.attributes.entry(name.to_string()).or_default().push(value.to_string());
+        self
+    }
+}
+
+/// Why an authentication attempt failed
+///
+/// Connectors that can tell apart the reasons a backend refuses a user report them, so the user can be told
+/// e.g. that their password must be changed. Connectors that can not tell report `InvalidCredentials`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthFailure {
+    /// The username or password is wrong, or the user does not exist
+    InvalidCredentials,
+    /// The account is locked, e.g. after too many failed attempts
+    AccountLocked,
+    /// The account has been disabled by an administrator
+    AccountDisabled,
+    /// The account has expired
+    AccountExpired,
+    /// The password has expired
+    PasswordExpired,
+    /// The password must be changed before the account can be used, e.g. after a reset by an administrator
+    PasswordMustChange,
+    /// The user is not allowed to log in at this time or from this workstation
+    LogonRestricted,
+    /// The password is right, but the user must also present a second factor
+    MfaRequired,
+}
+
+impl AuthFailure {
+    /// The error code reported to the client
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            AuthFailure::InvalidCredentials => ErrorCode::InvalidCredentials,
+            AuthFailure::AccountLocked => ErrorCode::AccountLocked,
+            AuthFailure::AccountDisabled => ErrorCode::AccountDisabled,
+            AuthFailure::AccountExpired => ErrorCode::AccountExpired,
+            AuthFailure::PasswordExpired => ErrorCode::PasswordExpired,
+            AuthFailure::PasswordMustChange => ErrorCode::PasswordChangeRequired,
+            AuthFailure::LogonRestricted => ErrorCode::LogonRestricted,
+            AuthFailure::MfaRequired => ErrorCode::MfaRequired,
+        }
+    }
+
+    /// A description for the user
+    pub fn description(&self) -> &'static str {
+        match self {
+            AuthFailure::InvalidCredentials => "Invalid credentials",
+            AuthFailure::AccountLocked => {
+                "Your account is locked. Please try again later or contact your administrator."
+            }
+            AuthFailure::AccountDisabled => "Your account is disabled. Please contact your administrator.",
+            AuthFailure::AccountExpired => "Your account has expired. Please contact your administrator.",
+            AuthFailure::PasswordExpired => "Your password has expired. Please change it and log in again.",
+            AuthFailure::PasswordMustChange => "Your password must be changed. Please change it and log in again.",
+            AuthFailure::LogonRestricted => "You are not allowed to log in at this time or from this workstation.",
+            AuthFailure::MfaRequired => "A second factor is required to log in.",
+        }
+    }
+}
+
+/// A connector could not answer whether a user is authenticated or what their permissions are
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// The backend could not be reached or failed to answer, e.g. a search failed
+    BackendUnavailable(String),
+    /// The user does not exist, e.g. because the account was deleted after the login
+    UnknownUser(String),
+    /// The user exists, but must not get tokens, e.g. because the account was disabled after the login
+    Denied(AuthFailure),
+    /// The connector is not in a state to answer, e.g. it has not been connected
+    Internal(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::BackendUnavailable(reason) => write!(f, "Backend unavailable: {}", reason),
+            AuthError::UnknownUser(username) => write!(f, "Unknown user: {}", username),
+            AuthError::Denied(failure) => write!(f, "Account can not be used: {:?}", failure),
+            AuthError::Internal(reason) => write!(f, "Internal error: {}", reason),
+        }
+    }
+}
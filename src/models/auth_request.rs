@@ -5,5 +5,6 @@ use crate::connectors::Connector;
 pub struct AuthRequest {
     pub username: String,
     pub password: String,
+    #[serde(default)]
     pub connector: Connector,
 }
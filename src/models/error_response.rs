@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+/// Stable, machine-readable error codes returned by the token endpoints
+///
+/// The codes are serialized in snake case, e.g. `invalid_credentials`, and will not change between
+/// releases. The accompanying description is meant for humans and may change.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request body is malformed or misses required fields
+    InvalidRequest,
+    /// The username or password is wrong
+    InvalidCredentials,
+    /// The account is locked, e.g. after too many failed attempts
+    AccountLocked,
+    /// The account has been disabled
+    AccountDisabled,
+    /// The account has expired
+    AccountExpired,
+    /// The password has expired and must be changed before logging in
+    PasswordExpired,
+    /// The password must be changed before logging in, e.g. after a reset by an administrator
+    PasswordChangeRequired,
+    /// The user is not allowed to log in at this time or from this workstation
+    LogonRestricted,
+    /// The user must also present a second factor
+    MfaRequired,
+    /// The refresh token is unknown, expired or has been revoked
+    InvalidGrant,
+    /// The refresh token has already been used. All refresh tokens of the same login have been revoked.
+    RefreshTokenReused,
+    /// The authentication backend could not be reached
+    TemporarilyUnavailable,
+    /// An unexpected error occurred while issuing the token
+    ServerError,
+}
+
+/// Error object returned by the token endpoints
+///
+/// ### Arguments
+/// * `error` - The error code
+/// * `error_description` - A human readable description of the error
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorCode,
+    pub error_description: String,
+}
+
+impl ErrorResponse {
+    pub fn new(error: ErrorCode, error_description: &str) -> ErrorResponse {
+        ErrorResponse {
+            error,
+            error_description: error_description.to_string(),
+        }
+    }
+}
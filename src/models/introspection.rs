@@ -0,0 +1,72 @@
+use crate::models::{JWTClaim, Permission};
+use serde::{Deserialize, Serialize};
+
+/// Body of a token introspection request, as defined by RFC 7662
+///
+/// ### Arguments
+/// * `token` - The token to introspect
+/// * `token_type_hint` - A hint about the type of the token. Only access tokens can be introspected.
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+}
+
+/// Token introspection response, as defined by RFC 7662
+///
+/// Inactive tokens only carry `active: false`, so no information about the token is disclosed.
+#[derive(Debug, Serialize, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<Permission>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<bool>,
+}
+
+impl IntrospectionResponse {
+    /// The response for a token that is not active
+    pub fn inactive() -> IntrospectionResponse {
+        IntrospectionResponse::default()
+    }
+}
+
+impl From<JWTClaim> for IntrospectionResponse {
+    fn from(claims: JWTClaim) -> Self {
+        IntrospectionResponse {
+            active: true,
+            token_type: Some("Bearer".to_string()),
+            username: Some(claims.sub.clone()),
+            sub: Some(claims.sub),
+            iss: Some(claims.iss),
+            aud: Some(claims.aud),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat as usize),
+            nbf: Some(claims.nbf),
+            jti: Some(claims.jti),
+            company: Some(claims.company),
+            permissions: Some(claims.permissions),
+            degraded: claims.degraded.then_some(true),
+        }
+    }
+}
@@ -1,23 +1,91 @@
 use crate::config::CONFIG;
-use crate::models::Permission;
+use crate::keys::KEY_RING;
+use crate::models::{Access, Permission};
+use crate::stores::random_token;
 use chrono::{Duration, Utc};
-use jsonwebtoken::errors::Error;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::errors::{Error, ErrorKind};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, TokenData, Validation};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 
+/// Characters of permission names that are percent-encoded in forward auth headers: everything but the
+/// unreserved characters of RFC 3986, so names can not contain the `,` and `:` separators or non-ASCII text
+const PERMISSION_NAME: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
 /// Claims struct
 ///
 /// ### Arguments
+/// * `iss` - The issuer of the token
 /// * `sub` - The subject of the token
+/// * `aud` - The audience the token is intended for
 /// * `company` - The company of the token
 /// * `exp` - The expiration of the token
+/// * `nbf` - The time before which the token must not be accepted
+/// * `iat` - The time the token was issued at, with millisecond precision, so a subject revocation can tell the
+///   tokens issued in the same second before and after it apart
+/// * `jti` - The unique identifier of the token, used to revoke it
 /// * `permissions` - The vector of permissions to be encoded in the token
+/// * `degraded` - The permissions could not be resolved completely, the user may lack some of them. Only present
+///   if set, with `PERMISSION_FAILURE_POLICY=degraded`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JWTClaim {
-    sub: String,
-    company: String,
-    exp: usize,
+    pub(crate) iss: String,
+    pub(crate) sub: String,
+    pub(crate) aud: String,
+    pub(crate) company: String,
+    pub(crate) exp: usize,
+    pub(crate) nbf: usize,
+    pub(crate) iat: f64,
+    pub(crate) jti: String,
     pub(crate) permissions: Vec<Permission>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) degraded: bool,
+}
+
+impl JWTClaim {
+    /// The time the token was issued at in milliseconds
+    pub(crate) fn issued_at_millis(&self) -> i64 {
+        (self.iat * 1000.0).round() as i64
+    }
+
+    /// Returns `true` if the token grants the permission
+    ///
+    /// # Arguments
+    /// * `name` - The name of the permission
+    /// * `access` - The access type the permission must grant, or `None` to accept any access type
+    pub fn grants(&self, name: &str, access: Option<&Access>) -> bool {
+        self.permissions
+            .iter()
+            .any(|permission| permission.name == name && (access.is_none() || access == Some(&permission.access_type)))
+    }
+
+    /// The identity headers returned to a reverse proxy when the token is used for forward auth
+    ///
+    /// The header names are configurable, headers with an empty name are left out. Permissions are
+    /// listed as comma separated `name:ACCESS` pairs, with the names percent-encoded.
+    ///
+    /// # Returns
+    /// * A vector of header name and value pairs
+    pub fn forward_auth_headers(&self) -> Vec<(String, String)> {
+        let permissions = self
+            .permissions
+            .iter()
+            .map(|permission| {
+                let name = utf8_percent_encode(&permission.name, PERMISSION_NAME);
+                format!("{}:{:?}", name, permission.access_type)
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        [
+            (&CONFIG.forward_auth_user_header, self.sub.clone()),
+            (&CONFIG.forward_auth_company_header, self.company.clone()),
+            (&CONFIG.forward_auth_permissions_header, permissions),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| name.as_ref().map(|name| (name.clone(), value)))
+        .collect()
+    }
 }
 
 /// This function is used to create a JWT token for a given user id.
@@ -25,50 +93,66 @@ pub struct JWTClaim {
 /// # Arguments
 ///
 /// * `user_id` - A string slice that holds the user id.
+/// * `permissions` - The permissions granted by the token.
+/// * `degraded` - Whether the permissions may be incomplete.
 ///
 /// # Returns
 ///
 /// * `Result<String, Error>` - This function returns a Result. If the token is successfully created,
-/// it returns the token as a String. If there is an error during the creation of the token, it returns the error.
+///   it returns the token as a String. If there is an error during the creation of the token, it returns the error.
 ///
 /// # Example
 ///
-/// ```
-/// let token = issue_token("user123", vec![]);
+/// ```no_run
+/// # use authio::models::jwt::issue_token;
+/// let token = issue_token("user123", vec![], false);
 /// match token {
 ///     Ok(t) => println!("Token: {}", t),
 ///     Err(e) => println!("Error: {}", e),
 /// }
 /// ```
-pub fn issue_token(user_id: &str, permissions: Vec<Permission>) -> Result<String, Error> {
+pub fn issue_token(user_id: &str, permissions: Vec<Permission>, degraded: bool) -> Result<String, Error> {
     // The expiration time for the token is retrieved from the configuration.
     let expiration_seconds: &u64 = &CONFIG.jwt_expiration_time_seconds;
     // The expiration time is calculated by adding the expiration seconds to the current time.
-    let expiration_time = Utc::now() + Duration::seconds(*expiration_seconds as i64);
+    let issued_at = Utc::now();
+    let expiration_time = issued_at + Duration::seconds(*expiration_seconds as i64);
 
     let claims = JWTClaim {
+        iss: CONFIG.jwt_issuer.clone(),
         sub: user_id.to_owned(),
+        aud: CONFIG.jwt_audience.clone(),
         company: CONFIG.jwt_company.clone(),
         exp: expiration_time.timestamp() as usize,
+        nbf: issued_at.timestamp() as usize,
+        iat: issued_at.timestamp_millis() as f64 / 1000.0,
+        jti: random_token(),
         permissions,
+        degraded,
     };
 
-
     log::debug!("Issue: {:?}", claims);
-    // The secret key for the JWT token is retrieved from the configuration.
-    let secret = &CONFIG.jwt_secret_key;
 
-    // The secret key is used to create an encoding key for the JWT token.
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
-
-    // The JWT token is encoded with the default header, the created claims, and the encoding key.
-    let issued_token = encode(&Header::default(), &claims, &encoding_key);
+    // The JWT token is signed with the active key of the key ring. The key id lets consumers
+    // pick the matching public key from the published key set.
+    let signing_key = KEY_RING.read().unwrap().active();
+    let mut header = Header::new(signing_key.algorithm);
+    header.kid = Some(signing_key.kid.clone());
+    let issued_token = encode(&header, &claims, &signing_key.encoding_key);
     log::debug!("Issued token: {:?}", issued_token);
     issued_token
 }
 
 /// Validates the provided JWT token.
 ///
+/// The registered claims are enforced: the issuer and audience must match the configuration, and
+/// the token must be within its `nbf` and `exp` window, allowing for the configured leeway.
+///
+/// The verification key is selected by the `kid` in the token header, so tokens signed by a
+/// retired key are accepted until they expire. Tokens without a `kid` are verified with the active
+/// key. Only the algorithm of the selected key is accepted, which prevents a token from being
+/// verified with a different algorithm than the one it was issued with.
+///
 /// # Arguments
 ///
 /// * `token_str` - The JWT token as a String.
@@ -79,9 +163,29 @@ pub fn issue_token(user_id: &str, permissions: Vec<Permission>) -> Result<String
 pub async fn validate_token(
     token_str: String,
 ) -> jsonwebtoken::errors::Result<TokenData<JWTClaim>> {
+    let header = decode_header(&token_str)?;
+    let verification_key = {
+        let key_ring = KEY_RING.read().unwrap();
+        match &header.kid {
+            Some(kid) => key_ring.find(kid).ok_or(Error::from(ErrorKind::InvalidSignature))?,
+            None => key_ring.active(),
+        }
+    };
+
     decode::<JWTClaim>(
         &token_str,
-        &DecodingKey::from_secret(&CONFIG.jwt_secret_key.as_ref()),
-        &Validation::default(),
+        &verification_key.decoding_key,
+        &validation(verification_key.algorithm),
     )
 }
+
+/// The validation rules for tokens signed with `algorithm`
+fn validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.set_issuer(&[&CONFIG.jwt_issuer]);
+    validation.set_audience(&[&CONFIG.jwt_audience]);
+    validation.validate_nbf = true;
+    validation.leeway = CONFIG.jwt_leeway_seconds;
+    validation
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+/// Body of a logout request
+///
+/// ### Arguments
+/// * `refresh_token` - The refresh token of the session, revoked together with the access token
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
@@ -1,9 +1,27 @@
 pub mod access;
+pub mod auth_outcome;
 pub mod auth_request;
+pub mod error_response;
+pub mod introspection;
 pub mod jwt;
+pub mod logout_request;
 pub mod permission;
+pub mod permission_query;
+pub mod refresh_request;
+pub mod resolved_permissions;
+pub mod revoke_request;
+pub mod token_response;
 
 pub use access::Access;
+pub use auth_outcome::{AuthError, AuthFailure, AuthOutcome, Principal};
 pub use auth_request::AuthRequest;
+pub use error_response::{ErrorCode, ErrorResponse};
+pub use introspection::{IntrospectionRequest, IntrospectionResponse};
 pub use jwt::JWTClaim;
+pub use logout_request::LogoutRequest;
 pub use permission::Permission;
+pub use permission_query::PermissionQuery;
+pub use refresh_request::RefreshRequest;
+pub use resolved_permissions::ResolvedPermissions;
+pub use revoke_request::RevokeRequest;
+pub use token_response::TokenResponse;
@@ -10,9 +10,13 @@ use serde::{Deserialize, Serialize};
 /// * `name` - The name of the permission
 /// * `description` - The description of the permission
 /// * `access_kind` - The type of access the permission grants.
-#[derive(Debug, Serialize, Deserialize)]
+/// * `id` - A stable identifier of the group granting the permission, which unlike the name survives renames and
+///   moves of the group. Only set with `LDAP_PERMISSION_ID`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Permission {
     pub(crate) name: String,
     pub(crate) description: String,
     pub(crate) access_type: Access,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
 }
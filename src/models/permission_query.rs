@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+/// Query parameters of a permission check
+///
+/// ### Arguments
+/// * `permission` - The name of the permission the token must grant
+/// * `access` - The access type the permission must grant. Any access type is accepted if not set.
+#[derive(Deserialize)]
+pub struct PermissionQuery {
+    pub permission: Option<String>,
+    pub access: Option<String>,
+}
@@ -0,0 +1,39 @@
+use crate::models::Permission;
+
+/// The permissions a connector resolved for a user
+///
+/// A connector that could look up the user, but not all of their groups, returns the permissions it found with the
+/// reason the rest is missing. That way a user whose groups could not be read is not mistaken for a user without
+/// groups.
+///
+/// ### Arguments
+/// * `permissions` - The permissions found
+/// * `failure` - Why permissions may be missing, None if all groups were looked up
+#[derive(Debug, Clone)]
+pub struct ResolvedPermissions {
+    pub permissions: Vec<Permission>,
+    pub failure: Option<String>,
+}
+
+impl ResolvedPermissions {
+    /// All permissions of the user, which may be none
+    pub fn complete(permissions: Vec<Permission>) -> ResolvedPermissions {
+        ResolvedPermissions {
+            permissions,
+            failure: None,
+        }
+    }
+
+    /// The permissions found before a lookup failed
+    pub fn partial(permissions: Vec<Permission>, failure: &str) -> ResolvedPermissions {
+        ResolvedPermissions {
+            permissions,
+            failure: Some(failure.to_string()),
+        }
+    }
+
+    /// Returns `true` if no lookup failed
+    pub fn is_complete(&self) -> bool {
+        self.failure.is_none()
+    }
+}
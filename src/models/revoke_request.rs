@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+/// Body of an admin revocation request
+///
+/// ### Arguments
+/// * `token` - An access token to revoke
+/// * `sub` - A subject whose access and refresh tokens are all revoked
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    pub token: Option<String>,
+    pub sub: Option<String>,
+}
@@ -0,0 +1,43 @@
+use crate::models::Permission;
+use serde::Serialize;
+
+/// Response of a successful login or token refresh, modeled after the OAuth2 access token response
+///
+/// ### Arguments
+/// * `access_token` - The signed JWT
+/// * `token_type` - Always `Bearer`
+/// * `expires_in` - The lifetime of the access token in seconds
+/// * `refresh_token` - A refresh token that can be exchanged for a new access token, if refresh tokens are enabled
+/// * `permissions` - The permissions granted by the access token
+/// * `degraded` - The permissions may be incomplete, because some could not be resolved
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub permissions: Vec<Permission>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+}
+
+impl TokenResponse {
+    /// Create a response for a bearer token
+    pub fn bearer(
+        access_token: String,
+        expires_in: u64,
+        refresh_token: Option<String>,
+        permissions: Vec<Permission>,
+        degraded: bool,
+    ) -> TokenResponse {
+        TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in,
+            refresh_token,
+            permissions,
+            degraded,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::models::JWTClaim;
+use crate::stores::subject_key;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// In-memory denylist of revoked access tokens
+///
+/// Access tokens are revoked by their `jti`, or all at once for a subject. A revoked token only
+/// has to be remembered until it would have expired anyway, so entries are pruned as soon as the
+/// token they refer to can no longer be valid.
+pub struct Denylist {
+    max_token_lifetime: i64,
+    leeway: i64,
+    state: Mutex<DenylistState>,
+}
+
+#[derive(Default)]
+struct DenylistState {
+    /// Revoked token ids, mapped to the expiry of the token
+    tokens: HashMap<String, i64>,
+    /// Revoked subjects by `subject_key`, mapped to the time of revocation in milliseconds. Tokens issued at or
+    /// before that time are revoked.
+    subjects: HashMap<String, i64>,
+}
+
+impl Denylist {
+    /// Create a new denylist
+    ///
+    /// # Arguments
+    /// * `max_token_lifetime_seconds` - The lifetime of an access token. A subject revocation is kept
+    ///   for this long, as every token issued before it has expired by then.
+    /// * `leeway_seconds` - How long after `exp` a token is still accepted
+    pub fn new(max_token_lifetime_seconds: u64, leeway_seconds: u64) -> Denylist {
+        Denylist {
+            max_token_lifetime: max_token_lifetime_seconds as i64,
+            leeway: leeway_seconds as i64,
+            state: Mutex::new(DenylistState::default()),
+        }
+    }
+
+    /// Revoke a single access token
+    pub fn revoke(&self, claims: &JWTClaim) {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state);
+
+        log::info!("Revoking token {} of user {}", claims.jti, claims.sub);
+        state.tokens.insert(claims.jti.clone(), claims.exp as i64);
+    }
+
+    /// Revoke every access token issued to a subject up until now
+    pub fn revoke_subject(&self, subject: &str) {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state);
+
+        log::info!("Revoking all tokens of user {}", subject);
+        state.subjects.insert(subject_key(subject), Utc::now().timestamp_millis());
+    }
+
+    /// Returns `true` if the token has been revoked
+    pub fn is_revoked(&self, claims: &JWTClaim) -> bool {
+        let state = self.state.lock().unwrap();
+
+        if state.tokens.contains_key(&claims.jti) {
+            return true;
+        }
+
+        match state.subjects.get(&subject_key(&claims.sub)) {
+            Some(revoked_at) => claims.issued_at_millis() <= *revoked_at,
+            None => false,
+        }
+    }
+
+    /// Drop the entries of tokens that have expired since they were revoked
+    fn prune(&self, state: &mut DenylistState) {
+        let now = Utc::now().timestamp();
+
+        state.tokens.retain(|_, expires_at| *expires_at + self.leeway > now);
+        state
+            .subjects
+            .retain(|_, revoked_at| *revoked_at / 1000 + self.max_token_lifetime + self.leeway > now);
+    }
+}
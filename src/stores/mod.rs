@@ -0,0 +1,24 @@
+pub mod denylist;
+pub mod refresh_tokens;
+
+pub use denylist::Denylist;
+pub use refresh_tokens::RefreshTokenStore;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use openssl::rand::rand_bytes;
+
+/// The form subjects are compared in when their tokens are revoked
+///
+/// The subject is the username as it was typed at login, and directories match usernames case-insensitively,
+/// so `JSmith` and `jsmith` are the same user.
+pub fn subject_key(subject: &str) -> String {
+    subject.to_lowercase()
+}
+
+/// Generate a random, URL safe token with 256 bits of entropy
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes).expect("The random number generator failed");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,161 @@
+use crate::connectors::Connector;
+use crate::stores::{random_token, subject_key};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use openssl::sha::sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A refresh token as it is kept by the store
+///
+/// ### Arguments
+/// * `family` - Identifies the chain of refresh tokens that originates from a single login
+/// * `subject` - The user the token was issued to
+/// * `connector` - The connector the user authenticated with
+/// * `expires_at` - The time after which the token can no longer be redeemed. Rotated tokens keep the expiry of
+///   the first token of their family, so a family can not be extended beyond the lifetime of the login.
+/// * `used` - Whether the token has already been exchanged for a new one
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub family: String,
+    pub subject: String,
+    pub connector: Connector,
+    expires_at: DateTime<Utc>,
+    used: bool,
+}
+
+/// Reasons a refresh token can not be redeemed
+#[derive(Debug, PartialEq)]
+pub enum RefreshError {
+    /// The token is unknown, expired or belongs to a revoked family
+    Invalid,
+    /// The token has been redeemed before. The whole family has been revoked.
+    Reused,
+}
+
+/// In-memory store of issued refresh tokens
+///
+/// Refresh tokens are opaque random strings. Only their SHA-256 hash is kept, so the store
+/// never holds a token that could be redeemed.
+///
+/// Every refresh token can be redeemed once. Redeeming it returns the token so a new one can be
+/// issued in the same family. Presenting a token that has already been redeemed means that it
+/// has been leaked, so every token of its family is revoked.
+pub struct RefreshTokenStore {
+    lifetime: Duration,
+    tokens: Mutex<HashMap<String, RefreshToken>>,
+}
+
+impl RefreshTokenStore {
+    /// Create a new store
+    ///
+    /// # Arguments
+    /// * `lifetime_seconds` - How long the refresh tokens of a login can be redeemed
+    pub fn new(lifetime_seconds: u64) -> RefreshTokenStore {
+        RefreshTokenStore {
+            lifetime: Duration::seconds(lifetime_seconds as i64),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issue a new refresh token
+    ///
+    /// # Arguments
+    /// * `subject` - The user the token is issued to
+    /// * `connector` - The connector the user authenticated with
+    /// * `rotated` - The redeemed token when rotating, `None` to start a new family
+    ///
+    /// # Returns
+    /// * The refresh token to hand out to the client, which expires with its family
+    pub fn issue(&self, subject: &str, connector: Connector, rotated: Option<&RefreshToken>) -> String {
+        let token = random_token();
+        let (family, expires_at) = match rotated {
+            Some(rotated) => (rotated.family.clone(), rotated.expires_at),
+            None => (random_token(), Utc::now() + self.lifetime),
+        };
+        let refresh_token = RefreshToken {
+            family,
+            subject: subject.to_owned(),
+            connector,
+            expires_at,
+            used: false,
+        };
+
+        let mut tokens = self.tokens.lock().unwrap();
+        // Expired tokens can not be redeemed, so there is no reason to keep them around
+        let now = Utc::now();
+        tokens.retain(|_, token| token.expires_at > now);
+        tokens.insert(hash(&token), refresh_token);
+
+        token
+    }
+
+    /// Redeem a refresh token
+    ///
+    /// The token is marked as used, and can not be redeemed again. If it was already used, the
+    /// whole family is revoked.
+    ///
+    /// # Arguments
+    /// * `token` - The refresh token presented by the client
+    ///
+    /// # Returns
+    /// * The redeemed token, or the reason it could not be redeemed
+    pub fn redeem(&self, token: &str) -> Result<RefreshToken, RefreshError> {
+        let mut tokens = self.tokens.lock().unwrap();
+
+        let refresh_token = match tokens.get_mut(&hash(token)) {
+            Some(refresh_token) if refresh_token.expires_at > Utc::now() => refresh_token,
+            _ => return Err(RefreshError::Invalid),
+        };
+
+        if refresh_token.used {
+            let family = refresh_token.family.clone();
+            log::warn!(
+                "Refresh token reuse detected for user {}. Revoking token family.",
+                refresh_token.subject
+            );
+            tokens.retain(|_, token| token.family != family);
+            return Err(RefreshError::Reused);
+        }
+
+        refresh_token.used = true;
+        Ok(refresh_token.clone())
+    }
+
+    /// Undo the redemption of a refresh token
+    ///
+    /// Used when a new token could not be issued after redeeming, e.g. because a backend was
+    /// unavailable, so the client can retry with the same token without it being seen as reused.
+    pub fn release(&self, token: &str) {
+        if let Some(refresh_token) = self.tokens.lock().unwrap().get_mut(&hash(token)) {
+            refresh_token.used = false;
+        }
+    }
+
+    /// Revoke the refresh token and every other token of its family
+    pub fn revoke(&self, token: &str) {
+        let mut tokens = self.tokens.lock().unwrap();
+
+        if let Some(family) = tokens.get(&hash(token)).map(|token| token.family.clone()) {
+            tokens.retain(|_, token| token.family != family);
+        }
+    }
+
+    /// Revoke every refresh token issued to a user, compared by `subject_key`
+    pub fn revoke_subject(&self, subject: &str) {
+        let subject = subject_key(subject);
+        self.tokens.lock().unwrap().retain(|_, token| subject_key(&token.subject) != subject);
+    }
+}
+
+impl RefreshToken {
+    /// The time after which the token, and every other token of its family, can no longer be redeemed
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+fn hash(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(sha256(token.as_bytes()))
+}
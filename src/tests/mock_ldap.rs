@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A minimal LDAP server, answering binds with a fixed result code and searches with fixed entries
+///
+/// The operations it receives are recorded as `bind <dn>` and `search <base>`, so tests can check which identity
+/// a search runs as.
+pub(crate) struct MockLdap {
+    pub(crate) url: String,
+    operations: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockLdap {
+    /// Start a server on a free port
+    ///
+    /// # Arguments
+    /// * `bind_rc` - The result code of every bind, e.g. 0 for success or 51 for busy
+    /// * `entries` - The DNs of the entries every search returns
+    pub(crate) async fn start(bind_rc: u8, entries: &[&str]) -> MockLdap {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ldap://{}", listener.local_addr().unwrap());
+        let operations = Arc::new(Mutex::new(vec![]));
+        let entries: Vec<String> = entries.iter().map(|dn| dn.to_string()).collect();
+
+        let recorded = operations.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, bind_rc, entries.clone(), recorded.clone()));
+            }
+        });
+
+        MockLdap { url, operations }
+    }
+
+    /// The operations received so far, in order
+    pub(crate) fn operations(&self) -> Vec<String> {
+        self.operations.lock().unwrap().clone()
+    }
+}
+
+async fn serve(mut stream: TcpStream, bind_rc: u8, entries: Vec<String>, operations: Arc<Mutex<Vec<String>>>) {
+    while let Some(message) = read_message(&mut stream).await {
+        // LDAPMessage ::= SEQUENCE { messageID INTEGER, protocolOp CHOICE { ... }, controls [0] OPTIONAL }
+        let Some((_, id, rest)) = ber_element(&message) else { return };
+        let Some((op, content, _)) = ber_element(rest) else { return };
+
+        let responses = match op {
+            // BindRequest ::= [APPLICATION 0] SEQUENCE { version INTEGER, name LDAPDN, authentication }
+            0x60 => {
+                let Some((_, _, rest)) = ber_element(content) else { return };
+                let Some((_, name, _)) = ber_element(rest) else { return };
+                operations.lock().unwrap().push(format!("bind {}", String::from_utf8_lossy(name)));
+                vec![ldap_result(0x61, bind_rc)]
+            }
+            // SearchRequest ::= [APPLICATION 3] SEQUENCE { baseObject LDAPDN, ... }
+            0x63 => {
+                let Some((_, base, _)) = ber_element(content) else { return };
+                operations.lock().unwrap().push(format!("search {}", String::from_utf8_lossy(base)));
+                let found = entries
+                    .iter()
+                    .map(|dn| element(0x64, &[element(0x04, dn.as_bytes()), element(0x30, &[])].concat()));
+                found.chain(std::iter::once(ldap_result(0x65, 0))).collect()
+            }
+            // UnbindRequest
+            0x42 => return,
+            _ => vec![],
+        };
+
+        for response in responses {
+            let message = element(0x30, &[element(0x02, id), response].concat());
+            if stream.write_all(&message).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// An LDAPResult with an empty matched DN and diagnostic message
+fn ldap_result(tag: u8, rc: u8) -> Vec<u8> {
+    element(tag, &[element(0x0a, &[rc]), element(0x04, b""), element(0x04, b"")].concat())
+}
+
+/// Encode a BER element
+fn element(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    match content.len() {
+        length if length < 0x80 => encoded.push(length as u8),
+        length => {
+            let octets: Vec<u8> = length.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+            encoded.push(0x80 | octets.len() as u8);
+            encoded.extend(octets);
+        }
+    }
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+/// Read the content of the next LDAPMessage, or None if the client closed the connection
+async fn read_message(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await.ok()?;
+    let length = match header[1] {
+        length if length < 0x80 => length as usize,
+        long_form => {
+            let mut octets = vec![0u8; (long_form & 0x7f) as usize];
+            stream.read_exact(&mut octets).await.ok()?;
+            octets.iter().fold(0usize, |length, byte| length << 8 | *byte as usize)
+        }
+    };
+    let mut content = vec![0u8; length];
+    stream.read_exact(&mut content).await.ok()?;
+    Some(content)
+}
+
+/// Split the first BER element off the input
+///
+/// # Returns
+/// * The tag, the content and the rest of the input, or None if the element is truncated
+fn ber_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let length = match first {
+        length if length < 0x80 => length as usize,
+        long_form => {
+            let octets = (long_form & 0x7f) as usize;
+            if input.len() < octets {
+                return None;
+            }
+            let length = input[..octets].iter().fold(0usize, |length, byte| length << 8 | *byte as usize);
+            input = &input[octets..];
+            length
+        }
+    };
+    if input.len() < length {
+        return None;
+    }
+    let (content, rest) = input.split_at(length);
+    Some((tag, content, rest))
+}
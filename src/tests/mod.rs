@@ -1,2 +1,47 @@
+use std::env;
+use std::sync::Once;
+
+pub(crate) mod mock_ldap;
 pub(crate) mod test_add;
+pub(crate) mod test_denylist;
+pub(crate) mod test_diagnostics;
+pub(crate) mod test_dn;
+pub(crate) mod test_dummy;
+pub(crate) mod test_groups;
+pub(crate) mod test_introspect;
+pub(crate) mod test_keys;
+pub(crate) mod test_ldap;
 pub(crate) mod test_login;
+pub(crate) mod test_mapping;
+pub(crate) mod test_object_id;
+pub(crate) mod test_outcome;
+pub(crate) mod test_pool;
+pub(crate) mod test_refresh_tokens;
+pub(crate) mod test_servers;
+pub(crate) mod test_tls;
+pub(crate) mod test_validate;
+
+static INIT: Once = Once::new();
+
+/// Provide the required configuration for tests that use the global `CONFIG`.
+///
+/// Must be called before `CONFIG` is first accessed. Every test uses the same values, so it does
+/// not matter which test initializes it.
+pub(crate) fn init_config() {
+    INIT.call_once(|| {
+        env::set_var("JWT_SECRET_KEY", "test");
+        env::set_var("JWT_PREVIOUS_SECRET_KEYS", "previous-test");
+        env::set_var("JWT_EXPIRATION_TIME_SECONDS", "3600");
+        env::set_var("JWT_COMPANY", "Example AB");
+        env::set_var("LDAP_URL", "ldap://localhost:389");
+        env::set_var("LDAP_ALLOW_PLAINTEXT", "true");
+        env::set_var("LDAP_BIND_DN", "cn=authio,dc=example,dc=com");
+        env::set_var("LDAP_BIND_PASSWORD", "secret");
+        env::set_var("HTTP_BIND_ADDRESS", "127.0.0.1");
+        env::set_var("HTTP_PORT", "8080");
+        env::set_var("AD_BASE_DN", "ou=people,dc=example,dc=com");
+        env::set_var("AD_FILTER_FORMAT", "(&(objectClass=*))");
+        env::set_var("AD_FILTER_ATTRS", "*,memberOf");
+        env::set_var("INTROSPECTION_API_KEY", "introspection-key");
+    });
+}
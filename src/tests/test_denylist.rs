@@ -0,0 +1,67 @@
+use authio::models::JWTClaim;
+use authio::stores::Denylist;
+use chrono::Utc;
+use serde_json::json;
+
+/// Claims of a token issued at `issued_at`, in milliseconds
+fn claims(sub: &str, jti: &str, issued_at: i64) -> JWTClaim {
+    serde_json::from_value(json!({
+        "iss": "authio",
+        "sub": sub,
+        "aud": "authio",
+        "company": "Example AB",
+        "exp": issued_at / 1000 + 60,
+        "nbf": issued_at / 1000,
+        "iat": issued_at as f64 / 1000.0,
+        "jti": jti,
+        "permissions": [],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_revoked_token_is_denied() {
+    let denylist = Denylist::new(60, 0);
+    let now = Utc::now().timestamp_millis();
+    let revoked = claims("tester", "first", now);
+
+    denylist.revoke(&revoked);
+
+    assert!(denylist.is_revoked(&revoked));
+    assert!(!denylist.is_revoked(&claims("tester", "second", now)));
+}
+
+#[test]
+fn test_revoked_subject_denies_earlier_tokens() {
+    let denylist = Denylist::new(60, 0);
+    let now = Utc::now().timestamp_millis();
+
+    denylist.revoke_subject("tester");
+
+    assert!(denylist.is_revoked(&claims("tester", "earlier", now - 10_000)));
+    assert!(!denylist.is_revoked(&claims("tester", "later", now + 10_000)));
+    assert!(!denylist.is_revoked(&claims("someone", "other", now - 10_000)));
+}
+
+#[test]
+fn test_revoked_subject_ignores_case() {
+    let denylist = Denylist::new(60, 0);
+    let now = Utc::now().timestamp_millis();
+
+    // The username as typed at login
+    denylist.revoke_subject("jsmith");
+
+    assert!(denylist.is_revoked(&claims("JSmith", "earlier", now - 10_000)));
+}
+
+#[test]
+fn test_revoked_subject_accepts_tokens_issued_in_the_same_second() {
+    let denylist = Denylist::new(60, 0);
+    denylist.revoke_subject("tester");
+    let revoked_at = Utc::now().timestamp_millis();
+
+    // Issued after the revocation, but in the same second
+    let second = revoked_at / 1000 * 1000;
+    assert!(!denylist.is_revoked(&claims("tester", "later", revoked_at + 1)));
+    assert!(denylist.is_revoked(&claims("tester", "earlier", second)));
+}
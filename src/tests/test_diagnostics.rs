@@ -0,0 +1,180 @@
+use crate::create_token;
+use crate::tests::init_config;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use authio::config::PermissionFailurePolicy;
+use authio::connectors::diagnostics::{
+    account_failure, ad_failure, bind_failure, hide_account_locked, password_policy_error, PASSWORD_POLICY_OID,
+};
+use authio::connectors::{Connector, ConnectorRegistry};
+use authio::models::{AuthError, AuthFailure, AuthOutcome, ResolvedPermissions};
+use authio::stores::RefreshTokenStore;
+use authio::traits::auth::Auth;
+use authio::traits::{Authenticate, Authorize};
+use ldap3::controls::{Control, RawControl};
+use ldap3::{LdapResult, SearchEntry};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A ppolicy response with the error changeAfterReset
+const CHANGE_AFTER_RESET: [u8; 5] = [0x30, 0x03, 0x81, 0x01, 0x02];
+
+fn bind_result(rc: u32, text: &str, ppolicy: Option<&[u8]>) -> LdapResult {
+    LdapResult {
+        rc,
+        matched: String::new(),
+        text: text.to_string(),
+        refs: vec![],
+        ctrls: ppolicy
+            .map(|value| {
+                Control(
+                    None,
+                    RawControl {
+                        ctype: PASSWORD_POLICY_OID.to_string(),
+                        crit: false,
+                        val: Some(value.to_vec()),
+                    },
+                )
+            })
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn ad_bind_result(sub_code: &str) -> LdapResult {
+    let text = format!(
+        "80090308: LdapErr: DSID-0C09044E, comment: AcceptSecurityContext error, data {}, v4563\0",
+        sub_code
+    );
+    bind_result(49, &text, None)
+}
+
+#[test]
+fn test_ad_bind_failures() {
+    assert_eq!(bind_failure(&ad_bind_result("52e")), Some(AuthFailure::InvalidCredentials));
+    // Unknown users look like a wrong password
+    assert_eq!(bind_failure(&ad_bind_result("525")), Some(AuthFailure::InvalidCredentials));
+    assert_eq!(bind_failure(&ad_bind_result("530")), Some(AuthFailure::LogonRestricted));
+    assert_eq!(bind_failure(&ad_bind_result("531")), Some(AuthFailure::LogonRestricted));
+    assert_eq!(bind_failure(&ad_bind_result("532")), Some(AuthFailure::PasswordExpired));
+    assert_eq!(bind_failure(&ad_bind_result("533")), Some(AuthFailure::AccountDisabled));
+    assert_eq!(bind_failure(&ad_bind_result("701")), Some(AuthFailure::AccountExpired));
+    assert_eq!(bind_failure(&ad_bind_result("773")), Some(AuthFailure::PasswordMustChange));
+    assert_eq!(bind_failure(&ad_bind_result("775")), Some(AuthFailure::AccountLocked));
+    assert_eq!(bind_failure(&ad_bind_result("999")), Some(AuthFailure::InvalidCredentials));
+    assert_eq!(ad_failure("52E"), Some(AuthFailure::InvalidCredentials));
+
+    // Other servers and other result codes
+    assert_eq!(bind_failure(&bind_result(49, "", None)), Some(AuthFailure::InvalidCredentials));
+    assert_eq!(bind_failure(&bind_result(0, "", None)), None);
+}
+
+#[test]
+fn test_password_policy_control() {
+    assert_eq!(password_policy_error(&[0x30, 0x03, 0x81, 0x01, 0x00]), Some(AuthFailure::PasswordExpired));
+    assert_eq!(password_policy_error(&[0x30, 0x03, 0x81, 0x01, 0x01]), Some(AuthFailure::AccountLocked));
+    assert_eq!(password_policy_error(&CHANGE_AFTER_RESET), Some(AuthFailure::PasswordMustChange));
+    // A warning of 3600 seconds before expiration, followed by the error
+    let warning = [0x30, 0x09, 0xa0, 0x04, 0x80, 0x02, 0x0e, 0x10, 0x81, 0x01, 0x01];
+    assert_eq!(password_policy_error(&warning), Some(AuthFailure::AccountLocked));
+    // Only a warning
+    assert_eq!(password_policy_error(&warning[..8]), None);
+    // Truncated
+    assert_eq!(password_policy_error(&[0x30, 0x05, 0x81, 0x01]), None);
+    assert_eq!(password_policy_error(&[]), None);
+
+    // The ppolicy overlay accepts the bind of an account whose password must be changed
+    assert_eq!(
+        bind_failure(&bind_result(0, "", Some(&CHANGE_AFTER_RESET))),
+        Some(AuthFailure::PasswordMustChange)
+    );
+    assert_eq!(
+        bind_failure(&bind_result(49, "", Some(&[0x30, 0x03, 0x81, 0x01, 0x01]))),
+        Some(AuthFailure::AccountLocked)
+    );
+}
+
+fn user_entry(attrs: &[(&str, &str)]) -> SearchEntry {
+    SearchEntry {
+        dn: "CN=tester,OU=people,DC=example,DC=com".to_string(),
+        attrs: attrs
+            .iter()
+            .map(|(attribute, value)| (attribute.to_string(), vec![value.to_string()]))
+            .collect::<HashMap<_, _>>(),
+        bin_attrs: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_account_failure() {
+    // NORMAL_ACCOUNT, NORMAL_ACCOUNT | ACCOUNTDISABLE
+    assert_eq!(account_failure(&user_entry(&[("userAccountControl", "512")])), None);
+    assert_eq!(account_failure(&user_entry(&[("userAccountControl", "514")])), Some(AuthFailure::AccountDisabled));
+    assert_eq!(account_failure(&user_entry(&[("useraccountcontrol", "66050")])), Some(AuthFailure::AccountDisabled));
+
+    let locked = user_entry(&[("pwdAccountLockedTime", "20240101120000Z")]);
+    assert_eq!(account_failure(&locked), Some(AuthFailure::AccountLocked));
+    assert_eq!(account_failure(&user_entry(&[("cn", "tester")])), None);
+}
+
+#[test]
+fn test_hide_account_locked() {
+    assert_eq!(hide_account_locked(AuthFailure::AccountLocked, false), AuthFailure::InvalidCredentials);
+    assert_eq!(hide_account_locked(AuthFailure::AccountLocked, true), AuthFailure::AccountLocked);
+
+    // Reported only once the password is right, so they do not give anything away
+    assert_eq!(hide_account_locked(AuthFailure::AccountDisabled, false), AuthFailure::AccountDisabled);
+    assert_eq!(hide_account_locked(AuthFailure::PasswordExpired, false), AuthFailure::PasswordExpired);
+}
+
+/// A connector that refuses every login with a fixed reason
+struct RefusingConnector(AuthFailure);
+
+impl Authenticate for RefusingConnector {
+    fn authenticate<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<AuthOutcome, AuthError>> + Send + 'a>> {
+        Box::pin(async { Ok(AuthOutcome::Denied(self.0)) })
+    }
+}
+
+impl Authorize for RefusingConnector {
+    fn resolve_permission<'a>(
+        &'a mut self,
+        _: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ResolvedPermissions, AuthError>> + Send + 'a>> {
+        Box::pin(async { Ok(ResolvedPermissions::complete(vec![])) })
+    }
+}
+
+impl Auth for RefusingConnector {}
+
+#[actix_web::test]
+async fn test_login_reports_failure() {
+    init_config();
+    let mut registry = ConnectorRegistry::new();
+    registry.register(Connector::Dummy, || Box::new(RefusingConnector(AuthFailure::PasswordMustChange)));
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(registry))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .service(create_token),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester", "password": "password", "connector": "Dummy"}))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["error"], "password_change_required");
+}
@@ -0,0 +1,87 @@
+use authio::connectors::dn::Dn;
+
+fn parse(dn: &str) -> Dn {
+    dn.parse().unwrap()
+}
+
+#[test]
+fn test_parse_dn() {
+    let dn = parse("CN=tool1,OU=tools,DC=example,DC=com");
+
+    assert_eq!(dn.rdns.len(), 4);
+    assert_eq!(dn.leaf().unwrap().value("cn"), Some("tool1"));
+    assert_eq!(dn.value("ou"), Some("tools"));
+    assert_eq!(dn.value("DC"), Some("example"));
+    assert_eq!(dn.value("uid"), None);
+}
+
+#[test]
+fn test_parse_dn_escapes() {
+    assert_eq!(parse("CN=Smith\\, John,OU=people").value("cn"), Some("Smith, John"));
+    assert_eq!(parse("CN=a\\+b\\=c\\\\d\\\"e,OU=x").value("cn"), Some("a+b=c\\d\"e"));
+    // Hex escapes form UTF-8
+    assert_eq!(parse("CN=J\\C3\\B6ns,OU=people").value("cn"), Some("Jöns"));
+    assert_eq!(parse("CN=\\23admins\\20,OU=x").value("cn"), Some("#admins "));
+}
+
+#[test]
+fn test_parse_dn_multi_valued_rdn() {
+    let dn = parse("CN=tester+UID=ttester,OU=people");
+
+    let leaf = dn.leaf().unwrap();
+    assert_eq!(leaf.attributes.len(), 2);
+    assert_eq!(leaf.value("uid"), Some("ttester"));
+    assert_eq!(dn.rdns.len(), 2);
+}
+
+#[test]
+fn test_parse_dn_spaces_and_oids() {
+    let dn = parse(" cn = tool1 , ou=tools ,2.5.4.3=x");
+
+    assert_eq!(dn.value("CN"), Some("tool1"));
+    assert_eq!(dn.value("OU"), Some("tools"));
+    assert_eq!(dn.value("2.5.4.3"), Some("x"));
+    assert_eq!(parse("CN=#04024869,OU=x").value("cn"), Some("#04024869"));
+    assert!(parse("").rdns.is_empty());
+}
+
+#[test]
+fn test_parse_invalid_dn() {
+    for dn in [
+        "tool1",
+        "CN=tool1,",
+        "CN=tool1,OU",
+        "=tool1",
+        "CN=a\\",
+        "CN=a\\zz",
+        "CN=a\"b",
+        "CN=a;b",
+        "CN=#abc",
+        "1CN=a",
+        "CN=\\FF",
+    ] {
+        assert!(dn.parse::<Dn>().is_err(), "{} should not parse", dn);
+    }
+}
+
+#[test]
+fn test_format_dn() {
+    let dn = parse("CN=Smith\\, John+UID=js,OU=people");
+    assert_eq!(dn.to_string(), "CN=Smith\\2c John+UID=js,OU=people");
+    assert_eq!(dn.to_string().parse::<Dn>().unwrap(), dn);
+}
+
+#[test]
+fn test_dn_matches() {
+    let dn = parse("CN=tool1,OU=tools,DC=example,DC=com");
+
+    assert!(dn.matches(&parse("cn=tool1,ou=tools,dc=example,dc=com")));
+    assert!(dn.matches(&parse("CN=tool1, OU=tools, DC=example, DC=com")));
+    assert!(dn.matches(&parse("CN=TOOL1,OU=tools,DC=example,DC=com")));
+    assert!(parse("CN=a+UID=b,DC=com").matches(&parse("uid=B+cn=A,dc=com")));
+    assert!(parse("CN=Smith\\, John,DC=com").matches(&parse("CN=Smith\\2c John,DC=com")));
+
+    assert!(!dn.matches(&parse("CN=tool1,OU=tools,DC=example")));
+    assert!(!dn.matches(&parse("CN=tool2,OU=tools,DC=example,DC=com")));
+    assert!(!parse("CN=a+UID=b,DC=com").matches(&parse("CN=a,DC=com")));
+}
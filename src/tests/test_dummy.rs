@@ -0,0 +1,107 @@
+use crate::create_token;
+use crate::tests::init_config;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use authio::config::PermissionFailurePolicy;
+use authio::connectors::dummy::{DummyConnector, DummyDirectory};
+use authio::connectors::{Connector, ConnectorRegistry};
+use authio::models::{Access, AuthError, AuthFailure, AuthOutcome, Principal};
+use authio::stores::RefreshTokenStore;
+use authio::traits::{Authenticate, Authorize};
+use serde_json::Value;
+use std::sync::Arc;
+
+const USERS_FILE: &str = "src/tests/dummy_users.json";
+
+fn registry() -> ConnectorRegistry {
+    let directory = Arc::new(DummyDirectory::load(USERS_FILE).unwrap());
+    let mut registry = ConnectorRegistry::new();
+    registry.register(Connector::Dummy, move || Box::new(DummyConnector::new(directory.clone())));
+    registry
+}
+
+#[actix_web::test]
+async fn test_dummy_authenticate() {
+    let mut connector = DummyConnector::new(Arc::new(DummyDirectory::load(USERS_FILE).unwrap()));
+
+    let denied = Ok(AuthOutcome::Denied(AuthFailure::InvalidCredentials));
+
+    assert_eq!(
+        connector.authenticate("tester", "password").await,
+        Ok(AuthOutcome::Authenticated(Principal::new("tester")))
+    );
+    assert_eq!(connector.authenticate("tester", "wrong").await, denied);
+    assert_eq!(connector.authenticate("unknown", "password").await, denied);
+    assert!(matches!(connector.authenticate("guest", "guest").await, Ok(AuthOutcome::Authenticated(_))));
+}
+
+#[actix_web::test]
+async fn test_dummy_permissions() {
+    let mut connector = DummyConnector::new(Arc::new(DummyDirectory::load(USERS_FILE).unwrap()));
+
+    let resolved = connector.resolve_permission("tester").await.unwrap();
+    assert!(resolved.is_complete());
+    let permissions = resolved.permissions;
+    let json = serde_json::to_value(&permissions).unwrap();
+    assert_eq!(json[0]["name"], "tool1");
+    assert_eq!(json[0]["access_type"], serde_json::to_value(Access::WRITE).unwrap());
+    assert_eq!(permissions.len(), 2);
+
+    assert!(connector.resolve_permission("guest").await.unwrap().permissions.is_empty());
+    let unknown = connector.resolve_permission("nobody").await.unwrap_err();
+    assert_eq!(unknown, AuthError::UnknownUser("nobody".to_string()));
+}
+
+#[test]
+fn test_dummy_rejects_duplicate_users() {
+    let json = r#"{"users": [{"username": "a", "password": "a"}, {"username": "a", "password": "b"}]}"#;
+    assert!(DummyDirectory::parse(json).is_err());
+}
+
+#[actix_web::test]
+async fn test_login_with_dummy_connector() {
+    init_config();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(registry()))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .service(create_token),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester", "password": "password", "connector": "Dummy"}))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["permissions"][1]["name"], "tool2");
+}
+
+#[actix_web::test]
+async fn test_login_with_disabled_connector() {
+    init_config();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(ConnectorRegistry::new()))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .service(create_token),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester", "password": "password", "connector": "Dummy"}))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_request");
+}
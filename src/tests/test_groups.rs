@@ -0,0 +1,147 @@
+use authio::config::{GroupLookup, NestedGroups};
+use authio::connectors::groups::{group_filter, in_chain_filter, member_filter, merge, uid, GroupWalk};
+use ldap3::SearchEntry;
+use std::collections::HashMap;
+
+const USER: &str = "CN=tester,OU=people,DC=example,DC=com";
+
+/// Walk a group hierarchy given as a map from a DN to the groups it is a direct member of
+fn walk(parents: &HashMap<&str, Vec<&str>>, direct_groups: Vec<&str>, max_depth: usize) -> Vec<String> {
+    let direct_groups = direct_groups.into_iter().map(String::from).collect();
+    let mut walk = GroupWalk::new(USER, direct_groups, max_depth);
+
+    while let Some(frontier) = walk.frontier() {
+        let found = frontier
+            .iter()
+            .flat_map(|dn| parents.get(dn.as_str()).cloned().unwrap_or_default())
+            .map(String::from)
+            .collect();
+        walk.visit(found);
+    }
+    walk.into_groups()
+}
+
+#[test]
+fn test_nested_groups_from_str() {
+    assert_eq!("none".parse::<NestedGroups>(), Ok(NestedGroups::None));
+    assert_eq!("IN_CHAIN".parse::<NestedGroups>(), Ok(NestedGroups::InChain));
+    assert_eq!("walk".parse::<NestedGroups>(), Ok(NestedGroups::Walk));
+    assert!("recursive".parse::<NestedGroups>().is_err());
+}
+
+#[test]
+fn test_group_walk_follows_nested_groups() {
+    let parents = HashMap::from([
+        (USER, vec!["CN=team-a,OU=groups"]),
+        ("CN=team-a,OU=groups", vec!["CN=tool1,OU=tools"]),
+        ("CN=tool1,OU=tools", vec!["CN=all-tools,OU=tools"]),
+    ]);
+
+    assert_eq!(
+        walk(&parents, vec![], 10),
+        vec!["CN=team-a,OU=groups", "CN=tool1,OU=tools", "CN=all-tools,OU=tools"]
+    );
+}
+
+#[test]
+fn test_group_walk_starts_from_direct_groups() {
+    let parents = HashMap::from([("CN=team-a,OU=groups", vec!["CN=tool1,OU=tools"])]);
+
+    assert_eq!(
+        walk(&parents, vec!["CN=team-a,OU=groups"], 10),
+        vec!["CN=team-a,OU=groups", "CN=tool1,OU=tools"]
+    );
+}
+
+#[test]
+fn test_group_walk_terminates_on_cycles() {
+    let parents = HashMap::from([
+        (USER, vec!["CN=a,OU=groups"]),
+        ("CN=a,OU=groups", vec!["CN=b,OU=groups"]),
+        // DNs are compared case-insensitively
+        ("CN=b,OU=groups", vec!["cn=a,ou=groups", "CN=c,OU=groups"]),
+        ("CN=c,OU=groups", vec!["CN=b,OU=groups"]),
+    ]);
+
+    assert_eq!(walk(&parents, vec![], 10), vec!["CN=a,OU=groups", "CN=b,OU=groups", "CN=c,OU=groups"]);
+}
+
+#[test]
+fn test_group_walk_depth_limit() {
+    let parents = HashMap::from([
+        (USER, vec!["CN=1"]),
+        ("CN=1", vec!["CN=2"]),
+        ("CN=2", vec!["CN=3"]),
+        ("CN=3", vec!["CN=4"]),
+    ]);
+
+    assert_eq!(walk(&parents, vec![], 2), vec!["CN=1", "CN=2"]);
+}
+
+#[test]
+fn test_member_filter() {
+    let template = "(|(member={dn})(uniqueMember={dn}))";
+
+    assert_eq!(
+        member_filter(template, &["CN=a,OU=groups".to_string()]),
+        "(|(member=CN=a,OU=groups)(uniqueMember=CN=a,OU=groups))"
+    );
+    assert_eq!(
+        member_filter("(member={dn})", &["CN=a".to_string(), "CN=b*)(x=y".to_string()]),
+        "(|(member=CN=a)(member=CN=b\\2a\\29\\28x=y))"
+    );
+}
+
+#[test]
+fn test_in_chain_filter() {
+    assert_eq!(
+        in_chain_filter("CN=tester (admin),OU=people"),
+        "(member:1.2.840.113556.1.4.1941:=CN=tester \\28admin\\29,OU=people)"
+    );
+}
+
+#[test]
+fn test_merge_groups() {
+    let merged = merge(
+        vec!["CN=a".to_string(), "CN=b".to_string()],
+        vec!["cn=A".to_string(), "CN=c".to_string()],
+    );
+    assert_eq!(merged, vec!["CN=a", "CN=b", "CN=c"]);
+}
+
+#[test]
+fn test_group_lookup_from_str() {
+    assert_eq!("member_of".parse::<GroupLookup>(), Ok(GroupLookup::MemberOf));
+    assert_eq!("Search".parse::<GroupLookup>(), Ok(GroupLookup::Search));
+    assert!("reverse".parse::<GroupLookup>().is_err());
+}
+
+#[test]
+fn test_group_filter() {
+    let template = "(|(member={dn})(memberUid={uid}))";
+
+    assert_eq!(
+        group_filter(template, USER, "tester"),
+        "(|(member=CN=tester,OU=people,DC=example,DC=com)(memberUid=tester))"
+    );
+    assert_eq!(
+        group_filter(template, "CN=a*,OU=people", "*)(uid=*"),
+        "(|(member=CN=a\\2a,OU=people)(memberUid=\\2a\\29\\28uid=\\2a))"
+    );
+}
+
+#[test]
+fn test_uid() {
+    let entry = SearchEntry {
+        dn: USER.to_string(),
+        attrs: HashMap::from([
+            ("cn".to_string(), vec!["tester".to_string()]),
+            ("UID".to_string(), vec!["ttester".to_string()]),
+        ]),
+        bin_attrs: HashMap::new(),
+    };
+    assert_eq!(uid(&entry), Some("ttester"));
+
+    let entry = SearchEntry { dn: USER.to_string(), attrs: HashMap::new(), bin_attrs: HashMap::new() };
+    assert_eq!(uid(&entry), None);
+}
@@ -0,0 +1,71 @@
+use crate::introspect;
+use crate::tests::init_config;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use authio::models::jwt::issue_token;
+use authio::stores::Denylist;
+use serde_json::Value;
+
+#[actix_web::test]
+async fn test_introspect_active_token() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(introspect),
+    )
+    .await;
+
+    let token = issue_token("tester", vec![], false).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/introspect")
+        .insert_header(("Authorization", "Bearer introspection-key"))
+        .set_form([("token", token.as_str())])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], "tester");
+    assert_eq!(body["aud"], "authio");
+}
+
+#[actix_web::test]
+async fn test_introspect_invalid_token() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(introspect),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/introspect")
+        .insert_header(("Authorization", "Bearer introspection-key"))
+        .set_form([("token", "not-a-token")])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body, serde_json::json!({"active": false}));
+}
+
+#[actix_web::test]
+async fn test_introspect_requires_api_key() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(introspect),
+    )
+    .await;
+
+    let token = issue_token("tester", vec![], false).unwrap();
+    for authorization in [None, Some("Bearer wrong-key")] {
+        let mut req = test::TestRequest::post().uri("/introspect");
+        if let Some(authorization) = authorization {
+            req = req.insert_header(("Authorization", authorization));
+        }
+        let resp = test::call_service(&app, req.set_form([("token", token.as_str())]).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
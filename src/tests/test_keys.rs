@@ -0,0 +1,118 @@
+use crate::tests::init_config;
+use authio::keys::{KeyRing, SigningKey};
+use authio::models::jwt::validate_token;
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+
+const ES256_PRIVATE_KEY: &[u8] = include_bytes!("keys/es256_private.pem");
+const ES256_PUBLIC_KEY: &[u8] = include_bytes!("keys/es256_public.pem");
+
+#[test]
+fn test_signing_key_from_pem() {
+    let key = SigningKey::from_pem(Algorithm::ES256, ES256_PRIVATE_KEY, ES256_PUBLIC_KEY);
+
+    assert!(key.is_ok());
+}
+
+#[test]
+fn test_signing_key_rejects_mismatched_algorithm() {
+    assert!(SigningKey::from_pem(Algorithm::HS256, ES256_PRIVATE_KEY, ES256_PUBLIC_KEY).is_err());
+    assert!(SigningKey::from_secret(Algorithm::RS256, b"secret").is_err());
+}
+
+#[test]
+fn test_signing_key_rejects_mismatched_key_pair() {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let other = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let other_public_key = other.public_key_to_pem().unwrap();
+
+    assert!(SigningKey::from_pem(Algorithm::ES256, ES256_PRIVATE_KEY, &other_public_key).is_err());
+}
+
+#[test]
+fn test_signing_key_publishes_public_jwk() {
+    let key = SigningKey::from_pem(Algorithm::ES256, ES256_PRIVATE_KEY, ES256_PUBLIC_KEY).unwrap();
+    let jwk = key.jwk.as_ref().expect("asymmetric keys are published");
+
+    // RFC 7638 thumbprint of the public key
+    assert_eq!(key.kid, "JRTAhSkzpEKFu0Xpuuus5nJLw1xEsdqMh38iGUsfl1Y");
+    assert_eq!(jwk.common.key_id.as_deref(), Some(key.kid.as_str()));
+    match &jwk.algorithm {
+        AlgorithmParameters::EllipticCurve(ec) => {
+            assert_eq!(ec.curve, EllipticCurve::P256);
+            assert_eq!(ec.x, "EHGbzeo9qDp_TMe030TM4HqB-LOURxGrkQ0aWqm3vqE");
+        }
+        other => panic!("Unexpected key parameters: {:?}", other),
+    }
+}
+
+#[test]
+fn test_signing_key_does_not_publish_secret() {
+    let key = SigningKey::from_secret(Algorithm::HS256, b"secret").unwrap();
+
+    assert!(key.jwk.is_none());
+    assert_eq!(key.with_kid("2024-01").kid, "2024-01");
+}
+
+#[test]
+fn test_key_ring_accepts_retired_keys() {
+    let first = SigningKey::generate(Algorithm::ES256).unwrap();
+    let second = SigningKey::generate(Algorithm::ES256).unwrap();
+    let (first_kid, second_kid) = (first.kid.clone(), second.kid.clone());
+
+    let mut key_ring = KeyRing::new(first);
+    key_ring.rotate(second, Duration::minutes(5));
+
+    assert_eq!(key_ring.active().kid, second_kid);
+    assert!(key_ring.find(&first_kid).is_some());
+    assert_eq!(key_ring.jwks().keys.len(), 2);
+}
+
+#[test]
+fn test_key_ring_drops_expired_keys() {
+    let first = SigningKey::generate(Algorithm::HS256).unwrap();
+    let second = SigningKey::generate(Algorithm::HS256).unwrap();
+    let first_kid = first.kid.clone();
+
+    let mut key_ring = KeyRing::new(first);
+    key_ring.rotate(second, Duration::zero());
+
+    assert!(key_ring.find(&first_kid).is_none());
+    assert!(key_ring.jwks().keys.is_empty());
+}
+
+#[test]
+fn test_key_ring_accepts_previous_secrets() {
+    let previous = SigningKey::from_secret(Algorithm::HS256, b"previous").unwrap();
+    let previous_kid = previous.kid.clone();
+
+    let mut key_ring = KeyRing::new(SigningKey::from_secret(Algorithm::HS256, b"current").unwrap());
+    key_ring.retire(previous, Duration::minutes(5));
+
+    assert_ne!(key_ring.active().kid, previous_kid);
+    assert!(key_ring.find(&previous_kid).is_some());
+    assert!(key_ring.jwks().keys.is_empty());
+}
+
+#[actix_web::test]
+async fn test_validate_token_signed_with_previous_secret() {
+    init_config();
+    let now = Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "iss": "authio", "sub": "tester", "aud": "authio", "company": "Example AB", "exp": now + 60, "nbf": now,
+        "iat": now, "jti": "previous", "permissions": [],
+    });
+    let sign = |secret: &[u8]| {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(SigningKey::from_secret(Algorithm::HS256, secret).unwrap().kid);
+        encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    };
+
+    // JWT_PREVIOUS_SECRET_KEYS, but not any other secret
+    assert!(validate_token(sign(b"previous-test")).await.is_ok());
+    assert!(validate_token(sign(b"unknown")).await.is_err());
+}
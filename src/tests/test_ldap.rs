@@ -0,0 +1,168 @@
+use crate::create_token;
+use crate::tests::init_config;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use crate::tests::mock_ldap::MockLdap;
+use authio::config::{LdapAuthMode, PermissionFailurePolicy, ServerSelection};
+use authio::connectors::ldap::LdapConnector;
+use authio::connectors::mapping::GroupMapping;
+use authio::connectors::pool::LdapPool;
+use authio::connectors::servers::ServerSet;
+use authio::connectors::username::{validate_username, MAX_USERNAME_LENGTH};
+use authio::connectors::ConnectorRegistry;
+use authio::stores::RefreshTokenStore;
+use authio::traits::auth::Auth;
+use ldap3::{LdapConnSettings, SearchEntry};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A connector with a connection to `url`
+async fn connect(url: &str) -> LdapConnector {
+    let servers = ServerSet::new(vec![url.to_string()], ServerSelection::Priority, Duration::from_secs(60));
+    let settings = LdapConnSettings::new();
+    let pool = LdapPool::new(servers, settings, 0, 1, Duration::from_secs(60), Duration::from_secs(1));
+    let mut connector = LdapConnector::new(Arc::new(pool), Arc::new(GroupMapping::default()));
+    connector.connect().await.unwrap();
+    connector
+}
+
+fn entry(dn: &str) -> SearchEntry {
+    SearchEntry {
+        dn: dn.to_string(),
+        attrs: HashMap::new(),
+        bin_attrs: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_ldap_auth_mode_from_str() {
+    assert_eq!("direct".parse::<LdapAuthMode>(), Ok(LdapAuthMode::Direct));
+    assert_eq!("Search".parse::<LdapAuthMode>(), Ok(LdapAuthMode::Search));
+    assert!("anonymous".parse::<LdapAuthMode>().is_err());
+}
+
+#[test]
+fn test_direct_bind_dn_escapes_username() {
+    init_config();
+
+    assert_eq!(LdapConnector::direct_bind_dn("tester"), "CN=tester,ou=people,dc=example,dc=com");
+    // Additional RDNs can not be injected
+    assert_eq!(
+        LdapConnector::direct_bind_dn("a,OU=admins"),
+        "CN=a\\2cOU\\3dadmins,ou=people,dc=example,dc=com"
+    );
+    // Multi-valued RDNs, quotes and escapes are neutralized
+    assert_eq!(
+        LdapConnector::direct_bind_dn("a+uid=admin\"\\"),
+        "CN=a\\2buid\\3dadmin\\22\\5c,ou=people,dc=example,dc=com"
+    );
+    // Leading '#' and leading or trailing spaces are escaped
+    assert_eq!(LdapConnector::direct_bind_dn("#a "), "CN=\\23a\\20,ou=people,dc=example,dc=com");
+    assert_eq!(LdapConnector::direct_bind_dn("<a>;b"), "CN=\\3ca\\3e\\3bb,ou=people,dc=example,dc=com");
+}
+
+#[test]
+fn test_user_search_filter_escapes_username() {
+    init_config();
+
+    assert_eq!(LdapConnector::user_search_filter("tester"), "(cn=tester)");
+    // Wildcards can not match other users
+    assert_eq!(LdapConnector::user_search_filter("*"), "(cn=\\2a)");
+    // Filter components can not be injected
+    assert_eq!(
+        LdapConnector::user_search_filter("admin)(|(cn=*"),
+        "(cn=admin\\29\\28|\\28cn=\\2a)"
+    );
+    assert_eq!(LdapConnector::user_search_filter("a\\b\0"), "(cn=a\\5cb\\00)");
+}
+
+#[test]
+fn test_validate_username() {
+    assert!(validate_username("tester").is_ok());
+    assert!(validate_username("tester.testersson@example.com").is_ok());
+    assert!(validate_username("Jöns Jacob").is_ok());
+
+    assert!(validate_username("").is_err());
+    assert!(validate_username("   ").is_err());
+    assert!(validate_username("tester\0").is_err());
+    assert!(validate_username("tester\r\nadmin").is_err());
+    assert!(validate_username(&"a".repeat(MAX_USERNAME_LENGTH)).is_ok());
+    assert!(validate_username(&"a".repeat(MAX_USERNAME_LENGTH + 1)).is_err());
+}
+
+#[actix_web::test]
+async fn test_login_rejects_invalid_username() {
+    init_config();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(ConnectorRegistry::new()))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .service(create_token),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester\u{0}", "password": "password"}))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_request");
+}
+
+#[test]
+fn test_unique_user_dn() {
+    let tester = "uid=tester,ou=people,dc=example,dc=com";
+
+    assert_eq!(LdapConnector::unique_user_dn("tester", vec![]), None);
+    assert_eq!(LdapConnector::unique_user_dn("tester", vec![entry(tester)]), Some(tester.to_string()));
+    // A filter that matches several users must not log in as any of them
+    let ambiguous = vec![entry(tester), entry("uid=tester,ou=admins,dc=example,dc=com")];
+    assert_eq!(LdapConnector::unique_user_dn("tester", ambiguous), None);
+}
+
+#[actix_web::test]
+async fn test_search_user_dn_as_service_account() {
+    init_config();
+    let tester = "uid=tester,ou=people,dc=example,dc=com";
+    let server = MockLdap::start(0, &[tester]).await;
+    let mut connector = connect(&server.url).await;
+
+    assert_eq!(connector.search_user_dn("tester").await, Ok(Some(tester.to_string())));
+
+    // The search runs right after binding as the service account, not as whoever was bound before
+    let operations = server.operations();
+    assert_eq!(
+        operations[operations.len() - 2..],
+        ["bind cn=authio,dc=example,dc=com", "search ou=people,dc=example,dc=com"]
+    );
+}
+
+#[actix_web::test]
+async fn test_search_user_dn_refuses_several_entries() {
+    init_config();
+    let server = MockLdap::start(0, &["cn=tester,ou=a,dc=example,dc=com", "cn=tester,ou=b,dc=example,dc=com"]).await;
+    let mut connector = connect(&server.url).await;
+
+    assert_eq!(connector.search_user_dn("tester").await, Ok(None));
+}
+
+#[actix_web::test]
+async fn test_search_user_dn_fails_without_service_account() {
+    init_config();
+    // 49: invalidCredentials, e.g. the service account password has been changed
+    let server = MockLdap::start(49, &["cn=tester,dc=example,dc=com"]).await;
+    let servers = ServerSet::new(vec![server.url.clone()], ServerSelection::Priority, Duration::from_secs(60));
+    let pool = LdapPool::new(servers, LdapConnSettings::new(), 0, 1, Duration::from_secs(60), Duration::from_secs(1));
+    let mut connector = LdapConnector::new(Arc::new(pool), Arc::new(GroupMapping::default()));
+
+    // No connection can be bound as the service account, so nothing is searched anonymously
+    assert!(connector.connect().await.is_err());
+    assert!(!server.operations().iter().any(|operation| operation.starts_with("search")));
+}
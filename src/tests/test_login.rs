@@ -1,5 +1,10 @@
-use actix_web::{http::header::ContentType, test, App};
-use crate::{ping};
+use actix_web::{http::header::ContentType, http::StatusCode, test, web, App};
+use crate::tests::init_config;
+use crate::{create_token, json_error_handler, ping, refresh_access_token};
+use authio::config::PermissionFailurePolicy;
+use authio::connectors::ConnectorRegistry;
+use authio::stores::RefreshTokenStore;
+use serde_json::Value;
 
 #[test]
 async fn test_index_get() {
@@ -11,3 +16,50 @@ async fn test_index_get() {
 
     assert!(resp.status().is_success());
 }
+
+#[actix_web::test]
+async fn test_login_malformed_body() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(ConnectorRegistry::new()))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .service(create_token),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_request");
+}
+
+#[actix_web::test]
+async fn test_refresh_invalid_token() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(ConnectorRegistry::new()))
+            .app_data(web::Data::new(PermissionFailurePolicy::Fail))
+            .service(refresh_access_token),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/token/refresh")
+        .set_json(serde_json::json!({"refresh_token": "unknown"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_grant");
+    assert!(body["error_description"].is_string());
+}
@@ -0,0 +1,219 @@
+use authio::config::PermissionName;
+use authio::connectors::groups::Group;
+use authio::connectors::mapping::GroupMapping;
+use serde_json::{json, Value};
+
+fn groups(dns: &[&str]) -> Vec<Group> {
+    dns.iter()
+        .map(|dn| Group {
+            dn: dn.to_string(),
+            description: None,
+            id: None,
+        })
+        .collect()
+}
+
+fn permissions(mapping: &GroupMapping, dns: &[&str]) -> Value {
+    let permissions = serde_json::to_value(mapping.permissions(&groups(dns))).unwrap();
+
+    // Only compare name and access
+    Value::Array(
+        permissions
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| json!([p["name"], p["access_type"]]))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_default_mapping() {
+    let mapping = GroupMapping::default();
+
+    assert_eq!(
+        permissions(&mapping, &["CN=tool1,OU=tools,DC=example,DC=com", "CN=tool2,OU=tools,DC=example,DC=com"]),
+        json!([["tool1", "READ"], ["tool2", "READ"]])
+    );
+}
+
+#[test]
+fn test_mapping_rules() {
+    let mapping = GroupMapping::parse(
+        r#"{
+            "rules": [
+                {
+                    "dn": "cn=tool1, ou=tools, dc=example, dc=com",
+                    "permissions": [{ "name": "tool1", "access": "WRITE" }]
+                },
+                { "cn": "TOOL2", "permissions": [{ "name": "tool2" }, { "name": "tool2-admin", "access": "EXECUTE" }] },
+                { "regex": "^CN=app-([^,]+)-admins,", "permissions": [{ "name": "$1", "access": "EXECUTE" }] }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        permissions(
+            &mapping,
+            &[
+                "CN=tool1,OU=tools,DC=example,DC=com",
+                "CN=tool2,OU=tools,DC=example,DC=com",
+                "CN=app-billing-admins,OU=groups,DC=example,DC=com",
+                "CN=Domain Users,OU=groups,DC=example,DC=com",
+            ]
+        ),
+        json!([
+            ["tool1", "WRITE"],
+            ["tool2", "READ"],
+            ["tool2-admin", "EXECUTE"],
+            ["billing", "EXECUTE"],
+            ["Domain Users", "READ"]
+        ])
+    );
+}
+
+#[test]
+fn test_mapping_drop_unmapped() {
+    let mapping = GroupMapping::parse(
+        r#"{
+            "drop_unmapped": true,
+            "rules": [
+                { "cn": "tool1", "permissions": [{ "name": "tool", "access": "WRITE" }] },
+                { "cn": "tool2", "permissions": [{ "name": "tool", "access": "WRITE" }] }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    // Duplicates are dropped
+    assert_eq!(
+        permissions(
+            &mapping,
+            &["CN=tool1,OU=tools", "CN=tool2,OU=tools", "CN=Domain Users,OU=groups"]
+        ),
+        json!([["tool", "WRITE"]])
+    );
+}
+
+#[test]
+fn test_mapping_invalid_rules() {
+    // No matcher
+    assert!(GroupMapping::parse(r#"{"rules": [{"permissions": []}]}"#).is_err());
+    // More than one matcher
+    assert!(GroupMapping::parse(r#"{"rules": [{"cn": "a", "dn": "CN=a", "permissions": []}]}"#).is_err());
+    // Invalid DN
+    assert!(GroupMapping::parse(r#"{"rules": [{"dn": "not a dn", "permissions": []}]}"#).is_err());
+    // Invalid regex
+    assert!(GroupMapping::parse(r#"{"rules": [{"regex": "(", "permissions": []}]}"#).is_err());
+    // Unknown access
+    let unknown_access = r#"{"rules": [{"cn": "a", "permissions": [{"name": "a", "access": "ALL"}]}]}"#;
+    assert!(GroupMapping::parse(unknown_access).is_err());
+}
+
+#[test]
+fn test_permission_name_from_str() {
+    assert_eq!("cn".parse::<PermissionName>(), Ok(PermissionName::Cn));
+    assert_eq!("DN".parse::<PermissionName>(), Ok(PermissionName::Dn));
+    assert_eq!("rdn:ou".parse::<PermissionName>(), Ok(PermissionName::Rdn("ou".to_string())));
+    assert!("rdn:".parse::<PermissionName>().is_err());
+    assert!("leaf".parse::<PermissionName>().is_err());
+}
+
+#[test]
+fn test_permission_names() {
+    let groups = ["CN=Smith\\, John,OU=tools,DC=example,DC=com", "OU=tools,DC=example,DC=com"];
+
+    assert_eq!(
+        permissions(&GroupMapping::default(), &groups),
+        json!([["Smith, John", "READ"], ["tools", "READ"]])
+    );
+    assert_eq!(
+        permissions(&GroupMapping::default().with_naming(PermissionName::Dn), &groups[..1]),
+        json!([["CN=Smith\\, John,OU=tools,DC=example,DC=com", "READ"]])
+    );
+    assert_eq!(
+        permissions(&GroupMapping::default().with_naming(PermissionName::Rdn("dc".to_string())), &groups),
+        json!([["example", "READ"]])
+    );
+}
+
+#[test]
+fn test_mapping_ignores_invalid_dn() {
+    assert_eq!(permissions(&GroupMapping::default(), &["tool1", "CN=tool2"]), json!([["tool2", "READ"]]));
+}
+
+#[test]
+fn test_permission_descriptions() {
+    let mapping = GroupMapping::parse(
+        r#"{
+            "rules": [
+                {
+                    "cn": "tool2",
+                    "permissions": [
+                        { "name": "tool2" },
+                        { "name": "tool2-admin", "description": "Tool 2 administration" }
+                    ]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut groups = groups(&["CN=tool1,OU=tools,DC=example,DC=com", "CN=tool2,OU=tools,DC=example,DC=com"]);
+    groups[1].description = Some("Tool 2 Group".to_string());
+
+    let permissions = serde_json::to_value(mapping.permissions(&groups)).unwrap();
+    let descriptions: Vec<&Value> = permissions.as_array().unwrap().iter().map(|p| &p["description"]).collect();
+
+    // Rule description, then group description, then group DN
+    assert_eq!(
+        descriptions,
+        [&json!("CN=tool1,OU=tools,DC=example,DC=com"), &json!("Tool 2 Group"), &json!("Tool 2 administration")]
+    );
+}
+
+#[test]
+fn test_permission_ids() {
+    let mut groups = groups(&["CN=tool1,OU=tools,DC=example,DC=com", "CN=tool1,OU=legacy,DC=example,DC=com"]);
+    groups[0].id = Some("3f2504e0-4f89-11d3-9a0c-0305e82c3301".to_string());
+    groups[1].id = Some("S-1-5-21-1004336348-1177238915-682003330-1105".to_string());
+
+    let permissions = serde_json::to_value(GroupMapping::default().permissions(&groups)).unwrap();
+    let ids: Vec<&Value> = permissions.as_array().unwrap().iter().map(|p| &p["id"]).collect();
+
+    // Groups with the same name but different identifiers are kept apart
+    assert_eq!(
+        ids,
+        [
+            &json!("3f2504e0-4f89-11d3-9a0c-0305e82c3301"),
+            &json!("S-1-5-21-1004336348-1177238915-682003330-1105")
+        ]
+    );
+    // Without an identifier it is left out
+    groups[0].id = None;
+    let permissions = serde_json::to_value(GroupMapping::default().permissions(&groups[..1])).unwrap();
+    assert!(permissions[0].get("id").is_none());
+}
+
+#[test]
+fn test_mapping_regex_rules() {
+    let mapping = GroupMapping::parse(
+        r#"{
+            "drop_unmapped": true,
+            "rules": [
+                {
+                    "regex": "^CN=app-([^,]+)-admins,",
+                    "permissions": [{ "name": "${1}_admin" }, { "name": "$1_admin" }]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    // OpenLDAP returns lowercase attribute types, the rule matches anyway. `$1_admin` expands to an empty name,
+    // which is skipped.
+    assert_eq!(
+        permissions(&mapping, &["cn=app-billing-admins,ou=groups,dc=example,dc=com"]),
+        json!([["billing_admin", "READ"]])
+    );
+}
@@ -0,0 +1,69 @@
+use authio::config::PermissionId;
+use authio::connectors::object_id::{format_guid, format_sid, read};
+use ldap3::SearchEntry;
+use std::collections::HashMap;
+
+const GUID: [u8; 16] = [
+    0xe0, 0x04, 0x25, 0x3f, 0x89, 0x4f, 0xd3, 0x11, 0x9a, 0x0c, 0x03, 0x05, 0xe8, 0x2c, 0x33, 0x01,
+];
+
+fn entry(attrs: HashMap<String, Vec<String>>, bin_attrs: HashMap<String, Vec<Vec<u8>>>) -> SearchEntry {
+    SearchEntry {
+        dn: "CN=tool1,OU=tools,DC=example,DC=com".to_string(),
+        attrs,
+        bin_attrs,
+    }
+}
+
+#[test]
+fn test_format_guid() {
+    assert_eq!(format_guid(&GUID), Some("3f2504e0-4f89-11d3-9a0c-0305e82c3301".to_string()));
+    assert_eq!(format_guid(&GUID[..15]), None);
+}
+
+#[test]
+fn test_format_sid() {
+    // BUILTIN\Administrators
+    let sid = [1, 2, 0, 0, 0, 0, 0, 5, 32, 0, 0, 0, 32, 2, 0, 0];
+    assert_eq!(format_sid(&sid), Some("S-1-5-32-544".to_string()));
+    // Sub-authority count does not match the length
+    assert_eq!(format_sid(&sid[..12]), None);
+    assert_eq!(format_sid(&[1]), None);
+}
+
+#[test]
+fn test_read_object_id() {
+    let binary = entry(HashMap::new(), HashMap::from([("objectGUID".to_string(), vec![GUID.to_vec()])]));
+    assert_eq!(
+        read(&binary, &PermissionId::ObjectGuid),
+        Some("3f2504e0-4f89-11d3-9a0c-0305e82c3301".to_string())
+    );
+    assert_eq!(read(&binary, &PermissionId::ObjectSid), None);
+
+    // ldap3 keeps binary values that happen to be valid UTF-8 with the text attributes
+    let text = entry(
+        HashMap::from([("objectguid".to_string(), vec!["abcdefghijklmnop".to_string()])]),
+        HashMap::new(),
+    );
+    assert_eq!(
+        read(&text, &PermissionId::ObjectGuid),
+        Some("64636261-6665-6867-696a-6b6c6d6e6f70".to_string())
+    );
+
+    let uuid = entry(
+        HashMap::from([("entryUUID".to_string(), vec!["5D3E0A4C-1B2F-4E6A-9C8D-7F6E5D4C3B2A".to_string()])]),
+        HashMap::new(),
+    );
+    assert_eq!(
+        read(&uuid, &PermissionId::EntryUuid),
+        Some("5d3e0a4c-1b2f-4e6a-9c8d-7f6e5d4c3b2a".to_string())
+    );
+}
+
+#[test]
+fn test_permission_id_from_str() {
+    assert_eq!("objectGUID".parse::<PermissionId>(), Ok(PermissionId::ObjectGuid));
+    assert_eq!("objectsid".parse::<PermissionId>(), Ok(PermissionId::ObjectSid));
+    assert_eq!("EntryUUID".parse::<PermissionId>(), Ok(PermissionId::EntryUuid));
+    assert!("uuid".parse::<PermissionId>().is_err());
+}
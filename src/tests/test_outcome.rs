@@ -0,0 +1,310 @@
+use crate::{apply_permission_policy, create_token, refresh_access_token};
+use crate::tests::init_config;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use authio::config::PermissionFailurePolicy;
+use authio::connectors::{Connector, ConnectorRegistry};
+use authio::models::jwt::{issue_token, validate_token};
+use authio::models::{
+    AuthError, AuthFailure, AuthOutcome, IntrospectionResponse, Permission, Principal, ResolvedPermissions,
+};
+use authio::stores::refresh_tokens::RefreshError;
+use authio::stores::RefreshTokenStore;
+use authio::traits::auth::Auth;
+use authio::traits::{Authenticate, Authorize};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A connector with fixed answers
+#[derive(Clone)]
+struct StubConnector {
+    connect: Result<(), AuthError>,
+    authenticate: Result<AuthOutcome, AuthError>,
+    permissions: Result<ResolvedPermissions, AuthError>,
+}
+
+impl Default for StubConnector {
+    fn default() -> Self {
+        StubConnector {
+            connect: Ok(()),
+            authenticate: Ok(AuthOutcome::Authenticated(Principal::new("tester"))),
+            permissions: Ok(ResolvedPermissions::complete(vec![])),
+        }
+    }
+}
+
+impl Authenticate for StubConnector {
+    fn authenticate<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<AuthOutcome, AuthError>> + Send + 'a>> {
+        Box::pin(async { self.authenticate.clone() })
+    }
+}
+
+impl Authorize for StubConnector {
+    fn resolve_permission<'a>(
+        &'a mut self,
+        _: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ResolvedPermissions, AuthError>> + Send + 'a>> {
+        Box::pin(async { self.permissions.clone() })
+    }
+}
+
+impl Auth for StubConnector {
+    fn connect(&mut self) -> Pin<Box<dyn Future<Output = Result<(), AuthError>> + Send + '_>> {
+        Box::pin(async { self.connect.clone() })
+    }
+}
+
+/// Log in with the stub connector and return the status and error code
+async fn login(connector: StubConnector) -> (StatusCode, Value) {
+    let (status, body) = login_with_policy(connector, PermissionFailurePolicy::Fail).await;
+    (status, body["error"].clone())
+}
+
+/// Log in with the stub connector under a permission failure policy and return the status and body
+async fn login_with_policy(connector: StubConnector, policy: PermissionFailurePolicy) -> (StatusCode, Value) {
+    init_config();
+    let mut registry = ConnectorRegistry::new();
+    registry.register(Connector::Dummy, move || Box::new(connector.clone()));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(RefreshTokenStore::new(3600)))
+            .app_data(web::Data::new(registry))
+            .app_data(web::Data::new(policy))
+            .service(create_token),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(serde_json::json!({"username": "tester", "password": "password", "connector": "Dummy"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status();
+    let body: Value = test::read_body_json(resp).await;
+    (status, body)
+}
+
+#[actix_web::test]
+async fn test_login_outcomes() {
+    let (status, error) = login(StubConnector::default()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(error, Value::Null);
+
+    let (status, error) = login(StubConnector {
+        authenticate: Ok(AuthOutcome::Denied(AuthFailure::MfaRequired)),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::UNAUTHORIZED, Some("mfa_required")));
+
+    let (status, error) = login(StubConnector {
+        authenticate: Ok(AuthOutcome::Denied(AuthFailure::AccountLocked)),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::FORBIDDEN, Some("account_locked")));
+}
+
+#[actix_web::test]
+async fn test_login_backend_errors() {
+    let unavailable = AuthError::BackendUnavailable("connection refused".to_string());
+
+    let (status, error) = login(StubConnector {
+        connect: Err(unavailable.clone()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("temporarily_unavailable")));
+
+    // A backend that fails is not mistaken for invalid credentials
+    let (status, error) = login(StubConnector {
+        authenticate: Err(unavailable.clone()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("temporarily_unavailable")));
+
+    // Nor for a user without permissions
+    let (status, error) = login(StubConnector {
+        permissions: Err(unavailable),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("temporarily_unavailable")));
+
+    let (status, error) = login(StubConnector {
+        authenticate: Err(AuthError::Internal("not connected".to_string())),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::INTERNAL_SERVER_ERROR, Some("server_error")));
+}
+
+fn permission(name: &str) -> Permission {
+    serde_json::from_value(serde_json::json!({"name": name, "description": name, "access_type": "READ"})).unwrap()
+}
+
+#[actix_web::test]
+async fn test_login_partial_permissions() {
+    // Permission lookups fail closed by default
+    let (status, error) = login(StubConnector {
+        permissions: Ok(ResolvedPermissions::partial(vec![permission("tool1")], "group search failed")),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!((status, error.as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("temporarily_unavailable")));
+
+    // A user without groups is not a failure
+    let (status, _) = login(StubConnector::default()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_login_permission_lookup_unavailable() {
+    // The user authenticated, but the backend failed before their permissions were looked up
+    let connector = || StubConnector {
+        permissions: Err(AuthError::BackendUnavailable("connection reset".to_string())),
+        ..Default::default()
+    };
+
+    let (status, body) = login_with_policy(connector(), PermissionFailurePolicy::Fail).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"], "temporarily_unavailable");
+
+    let (status, body) = login_with_policy(connector(), PermissionFailurePolicy::Degraded).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["degraded"], true);
+    assert_eq!(body["permissions"], serde_json::json!([]));
+
+    let (status, body) = login_with_policy(connector(), PermissionFailurePolicy::Proceed).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("degraded").is_none());
+    assert_eq!(body["permissions"], serde_json::json!([]));
+
+    // Other errors are never subject to the policy
+    let (status, body) = login_with_policy(
+        StubConnector {
+            permissions: Err(AuthError::Internal("bug".to_string())),
+            ..Default::default()
+        },
+        PermissionFailurePolicy::Proceed,
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["error"], "server_error");
+}
+
+#[actix_web::test]
+async fn test_permission_failure_policy() {
+    let partial = || Ok(ResolvedPermissions::partial(vec![permission("tool1")], "group search failed"));
+    let unavailable = || Err(AuthError::BackendUnavailable("connection refused".to_string()));
+
+    let resolved = apply_permission_policy(PermissionFailurePolicy::Fail, "tester", partial());
+    assert!(matches!(resolved, Err(AuthError::BackendUnavailable(_))));
+
+    let resolved = apply_permission_policy(PermissionFailurePolicy::Degraded, "tester", partial()).unwrap();
+    assert!(!resolved.is_complete());
+    assert_eq!(resolved.permissions.len(), 1);
+
+    let resolved = apply_permission_policy(PermissionFailurePolicy::Proceed, "tester", partial()).unwrap();
+    assert!(resolved.is_complete());
+    assert_eq!(resolved.permissions.len(), 1);
+
+    // Complete permissions are never affected, errors always fail
+    for policy in [PermissionFailurePolicy::Fail, PermissionFailurePolicy::Degraded, PermissionFailurePolicy::Proceed] {
+        let resolved = apply_permission_policy(policy, "tester", Ok(ResolvedPermissions::complete(vec![]))).unwrap();
+        assert!(resolved.is_complete());
+        let resolved = apply_permission_policy(policy, "tester", unavailable());
+        assert!(matches!(resolved, Err(AuthError::BackendUnavailable(_))));
+        let resolved = apply_permission_policy(policy, "tester", Err(AuthError::Internal("bug".to_string())));
+        assert!(matches!(resolved, Err(AuthError::Internal(_))));
+    }
+}
+
+#[actix_web::test]
+async fn test_degraded_token() {
+    init_config();
+
+    let token = issue_token("tester", vec![], true).unwrap();
+    let claims = validate_token(token).await.unwrap().claims;
+    let introspection = serde_json::to_value(IntrospectionResponse::from(claims)).unwrap();
+    assert_eq!(introspection["degraded"], true);
+
+    // Complete tokens do not carry the claim at all
+    let token = issue_token("tester", vec![], false).unwrap();
+    let claims = validate_token(token).await.unwrap().claims;
+    let introspection = serde_json::to_value(IntrospectionResponse::from(claims)).unwrap();
+    assert!(introspection.get("degraded").is_none());
+}
+
+/// Refresh a token of "tester" with the stub connector resolving the given permissions
+///
+/// The most lenient permission failure policy is configured, so none of the outcomes depend on it.
+///
+/// # Returns
+/// * The status and error code of the response, the store and the refresh token that was presented
+async fn refresh(
+    permissions: Result<ResolvedPermissions, AuthError>,
+) -> (StatusCode, Value, web::Data<RefreshTokenStore>, String) {
+    init_config();
+    let refresh_tokens = web::Data::new(RefreshTokenStore::new(3600));
+    let mut registry = ConnectorRegistry::new();
+    registry.register(Connector::Dummy, move || {
+        Box::new(StubConnector {
+            permissions: permissions.clone(),
+            ..Default::default()
+        })
+    });
+    let app = test::init_service(
+        App::new()
+            .app_data(refresh_tokens.clone())
+            .app_data(web::Data::new(registry))
+            .app_data(web::Data::new(PermissionFailurePolicy::Proceed))
+            .service(refresh_access_token),
+    )
+    .await;
+
+    let token = refresh_tokens.issue("tester", Connector::Dummy, None);
+    let req = test::TestRequest::post()
+        .uri("/token/refresh")
+        .set_json(serde_json::json!({"refresh_token": token}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status();
+    let body: Value = test::read_body_json(resp).await;
+    (status, body["error"].clone(), refresh_tokens, token)
+}
+
+#[actix_web::test]
+async fn test_refresh_deleted_user() {
+    let (status, error, refresh_tokens, token) = refresh(Err(AuthError::UnknownUser("tester".to_string()))).await;
+    assert_eq!((status, error.as_str()), (StatusCode::UNAUTHORIZED, Some("invalid_grant")));
+
+    // The family is revoked, not released for another attempt
+    assert_eq!(refresh_tokens.redeem(&token).unwrap_err(), RefreshError::Invalid);
+}
+
+#[actix_web::test]
+async fn test_refresh_disabled_user() {
+    for failure in [AuthFailure::AccountDisabled, AuthFailure::AccountLocked] {
+        let (status, error, refresh_tokens, token) = refresh(Err(AuthError::Denied(failure))).await;
+        assert_eq!((status, error.as_str()), (StatusCode::UNAUTHORIZED, Some("invalid_grant")));
+        assert_eq!(refresh_tokens.redeem(&token).unwrap_err(), RefreshError::Invalid);
+    }
+}
+
+#[actix_web::test]
+async fn test_refresh_backend_unavailable() {
+    // No token is issued without asking the backend, whatever the permission failure policy
+    let unavailable = AuthError::BackendUnavailable("connection refused".to_string());
+    let (status, error, refresh_tokens, token) = refresh(Err(unavailable)).await;
+    assert_eq!((status, error.as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("temporarily_unavailable")));
+
+    // The client can retry with the same token
+    assert!(refresh_tokens.redeem(&token).is_ok());
+}
@@ -0,0 +1,77 @@
+use crate::tests::init_config;
+use crate::tests::mock_ldap::MockLdap;
+use authio::config::ServerSelection;
+use authio::connectors::pool::LdapPool;
+use authio::connectors::servers::ServerSet;
+use ldap3::LdapConnSettings;
+use std::time::Duration;
+
+/// Nothing listens on port 1, connections are refused immediately
+const UNREACHABLE_URL: &str = "ldap://127.0.0.1:1";
+
+fn servers(urls: &[&str]) -> ServerSet {
+    let urls = urls.iter().map(|url| url.to_string()).collect();
+    ServerSet::new(urls, ServerSelection::Priority, Duration::from_secs(60))
+}
+
+/// A pool of at most two connections that gives up waiting for one after a second
+fn pool(urls: &[&str], min_size: usize) -> LdapPool {
+    let (idle_timeout, acquire_timeout) = (Duration::from_secs(60), Duration::from_secs(1));
+    LdapPool::new(servers(urls), LdapConnSettings::new(), min_size, 2, idle_timeout, acquire_timeout)
+}
+
+#[actix_web::test]
+async fn test_pool_unreachable_server() {
+    let pool = pool(&[UNREACHABLE_URL], 0);
+
+    assert!(pool.get().await.is_none());
+    // The failed checkout does not hold on to its slot
+    assert_eq!(pool.in_use(), 0);
+    assert_eq!(pool.idle_count(), 0);
+}
+
+#[actix_web::test]
+async fn test_pool_maintain_unreachable_server() {
+    let pool = pool(&[UNREACHABLE_URL], 2);
+
+    // Gives up instead of retrying until the minimum size is reached
+    pool.maintain().await;
+    assert_eq!(pool.idle_count(), 0);
+}
+
+#[actix_web::test]
+async fn test_pool_fails_over_to_next_server() {
+    let unreachable = ["ldap://127.0.0.1:1", "ldap://127.0.0.1:2"];
+    let pool = pool(&unreachable, 0);
+
+    // Every server is tried, and marked unhealthy when it refuses the connection
+    assert!(pool.get().await.is_none());
+    assert!(!pool.servers().is_healthy(unreachable[0]));
+    assert!(!pool.servers().is_healthy(unreachable[1]));
+}
+
+#[actix_web::test]
+async fn test_pool_fails_over_to_server_that_accepts() {
+    init_config();
+    let server = MockLdap::start(0, &[]).await;
+    let pool = pool(&[UNREACHABLE_URL, &server.url], 0);
+
+    assert!(pool.get().await.is_some());
+    assert!(!pool.servers().is_healthy(UNREACHABLE_URL));
+    assert!(pool.servers().is_healthy(&server.url));
+    assert_eq!(server.operations(), vec!["bind cn=authio,dc=example,dc=com"]);
+}
+
+#[actix_web::test]
+async fn test_pool_fails_over_when_service_bind_fails() {
+    init_config();
+    // 51: busy
+    let busy = MockLdap::start(51, &[]).await;
+    let server = MockLdap::start(0, &[]).await;
+    let pool = pool(&[&busy.url, &server.url], 0);
+
+    assert!(pool.get().await.is_some());
+    assert!(!pool.servers().is_healthy(&busy.url));
+    assert!(pool.servers().is_healthy(&server.url));
+    assert_eq!(busy.operations(), vec!["bind cn=authio,dc=example,dc=com"]);
+}
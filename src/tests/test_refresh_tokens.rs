@@ -0,0 +1,60 @@
+use authio::connectors::Connector;
+use authio::stores::refresh_tokens::RefreshError;
+use authio::stores::RefreshTokenStore;
+
+#[test]
+fn test_refresh_token_rotation() {
+    let store = RefreshTokenStore::new(60);
+    let token = store.issue("tester", Connector::Ldap, None);
+
+    let redeemed = store.redeem(&token).unwrap();
+    assert_eq!(redeemed.subject, "tester");
+
+    let rotated = store.issue(&redeemed.subject, redeemed.connector.clone(), Some(&redeemed));
+    let rotated = store.redeem(&rotated).unwrap();
+    assert_eq!(rotated.family, redeemed.family);
+    // Rotating does not extend the lifetime of the family
+    assert_eq!(rotated.expires_at(), redeemed.expires_at());
+}
+
+#[test]
+fn test_refresh_token_reuse_revokes_family() {
+    let store = RefreshTokenStore::new(60);
+    let token = store.issue("tester", Connector::Ldap, None);
+    let redeemed = store.redeem(&token).unwrap();
+    let rotated = store.issue("tester", Connector::Ldap, Some(&redeemed));
+
+    assert_eq!(store.redeem(&token).unwrap_err(), RefreshError::Reused);
+    assert_eq!(store.redeem(&rotated).unwrap_err(), RefreshError::Invalid);
+}
+
+#[test]
+fn test_released_refresh_token_can_be_redeemed() {
+    let store = RefreshTokenStore::new(60);
+    let token = store.issue("tester", Connector::Ldap, None);
+
+    store.redeem(&token).unwrap();
+    store.release(&token);
+
+    assert!(store.redeem(&token).is_ok());
+}
+
+#[test]
+fn test_expired_refresh_token_is_invalid() {
+    let store = RefreshTokenStore::new(0);
+    let token = store.issue("tester", Connector::Ldap, None);
+
+    assert_eq!(store.redeem(&token).unwrap_err(), RefreshError::Invalid);
+}
+
+#[test]
+fn test_revoke_subject_ignores_case() {
+    let store = RefreshTokenStore::new(60);
+    let token = store.issue("JSmith", Connector::Ldap, None);
+    let other = store.issue("someone", Connector::Ldap, None);
+
+    store.revoke_subject("jsmith");
+
+    assert_eq!(store.redeem(&token).unwrap_err(), RefreshError::Invalid);
+    assert!(store.redeem(&other).is_ok());
+}
@@ -0,0 +1,109 @@
+use authio::config::ServerSelection;
+use authio::connectors::servers::{ServerSet, SrvRecord, SrvResolver};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Stands in for DNS with fixed SRV records
+struct StaticResolver {
+    records: HashMap<String, Vec<SrvRecord>>,
+}
+
+impl SrvResolver for StaticResolver {
+    fn lookup<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SrvRecord>, String>> + Send + 'a>> {
+        Box::pin(async move { self.records.get(name).cloned().ok_or(format!("NXDOMAIN: {}", name)) })
+    }
+}
+
+fn record(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+    SrvRecord {
+        priority,
+        weight,
+        port,
+        target: target.to_string(),
+    }
+}
+
+fn urls(urls: &[&str]) -> Vec<String> {
+    urls.iter().map(|url| url.to_string()).collect()
+}
+
+#[actix_web::test]
+async fn test_priority_selection() {
+    let dcs = urls(&["ldaps://dc1", "ldaps://dc2", "ldaps://dc3"]);
+    let servers = ServerSet::new(dcs, ServerSelection::Priority, RETRY_AFTER);
+
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc1", "ldaps://dc2", "ldaps://dc3"]));
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc1", "ldaps://dc2", "ldaps://dc3"]));
+
+    // Unhealthy servers are tried last, the one that failed longest ago first
+    servers.mark_unhealthy("ldaps://dc2");
+    servers.mark_unhealthy("ldaps://dc1");
+    assert!(!servers.is_healthy("ldaps://dc1"));
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc3", "ldaps://dc2", "ldaps://dc1"]));
+
+    servers.mark_healthy("ldaps://dc1");
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc1", "ldaps://dc3", "ldaps://dc2"]));
+}
+
+#[actix_web::test]
+async fn test_round_robin_selection() {
+    let dcs = urls(&["ldaps://dc1", "ldaps://dc2", "ldaps://dc3"]);
+    let servers = ServerSet::new(dcs, ServerSelection::RoundRobin, RETRY_AFTER);
+
+    assert_eq!(servers.candidates().await[0], "ldaps://dc1");
+    assert_eq!(servers.candidates().await[0], "ldaps://dc2");
+    servers.mark_unhealthy("ldaps://dc3");
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc1", "ldaps://dc2", "ldaps://dc3"]));
+    assert_eq!(servers.candidates().await[0], "ldaps://dc1");
+}
+
+#[actix_web::test]
+async fn test_unhealthy_server_is_retried() {
+    let servers = ServerSet::new(urls(&["ldaps://dc1", "ldaps://dc2"]), ServerSelection::Priority, Duration::ZERO);
+
+    servers.mark_unhealthy("ldaps://dc1");
+    assert!(servers.is_healthy("ldaps://dc1"));
+    assert_eq!(servers.candidates().await, urls(&["ldaps://dc1", "ldaps://dc2"]));
+}
+
+#[actix_web::test]
+async fn test_srv_discovery() {
+    let resolver = StaticResolver {
+        records: HashMap::from([(
+            "_ldap._tcp.example.com".to_string(),
+            vec![
+                record(10, 0, 389, "backup.example.com."),
+                record(0, 10, 389, "dc2.example.com."),
+                record(0, 50, 3268, "dc1.example.com."),
+            ],
+        )]),
+    };
+    let servers = ServerSet::srv("example.com", false, Box::new(resolver), ServerSelection::Priority, RETRY_AFTER);
+
+    // By ascending priority and descending weight
+    assert_eq!(
+        servers.candidates().await,
+        urls(&["ldap://dc1.example.com:3268", "ldap://dc2.example.com:389", "ldap://backup.example.com:389"])
+    );
+}
+
+#[actix_web::test]
+async fn test_srv_discovery_without_servers() {
+    let resolver = StaticResolver {
+        records: HashMap::from([("_ldaps._tcp.example.com".to_string(), vec![record(0, 0, 0, ".")])]),
+    };
+    let servers = ServerSet::srv("example.com", true, Box::new(resolver), ServerSelection::Priority, RETRY_AFTER);
+    assert!(servers.candidates().await.is_empty());
+
+    // The lookup fails
+    let resolver = StaticResolver { records: HashMap::new() };
+    let servers = ServerSet::srv("example.com", true, Box::new(resolver), ServerSelection::Priority, RETRY_AFTER);
+    assert!(servers.candidates().await.is_empty());
+}
@@ -0,0 +1,119 @@
+use authio::config::check_ldap_transport;
+use authio::connectors::tls::{build_conn_settings, read_ca_bundle};
+use ldap3::LdapConnAsync;
+use native_tls::{Identity, TlsAcceptor};
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509NameBuilder, X509};
+use std::net::TcpListener;
+
+/// A self-signed certificate for `hostname` and its PKCS#8 key, both PEM encoded
+fn certificate(hostname: &str) -> (Vec<u8>, Vec<u8>) {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", hostname).unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+    let alternative_name = SubjectAlternativeName::new()
+        .dns(hostname)
+        .build(&builder.x509v3_context(None, None))
+        .unwrap();
+    builder.append_extension(alternative_name).unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+    (builder.build().to_pem().unwrap(), key.private_key_to_pem_pkcs8().unwrap())
+}
+
+/// Start a TLS server with a certificate for `hostname`
+///
+/// # Returns
+/// * The `ldaps://localhost` URL of the server and the path of its certificate, to be trusted as CA
+fn start_tls_server(hostname: &str) -> (String, String) {
+    let (cert, key) = certificate(hostname);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let ca_file = std::env::temp_dir().join(format!("authio-test-ca-{}.pem", port));
+    std::fs::write(&ca_file, &cert).unwrap();
+
+    let acceptor = TlsAcceptor::new(Identity::from_pkcs8(&cert, &key).unwrap()).unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Clients that reject the certificate abort the handshake
+            let _ = acceptor.accept(stream);
+        }
+    });
+
+    (format!("ldaps://localhost:{}", port), ca_file.to_string_lossy().into_owned())
+}
+
+#[test]
+fn test_refuses_plaintext_ldap() {
+    assert!(check_ldap_transport(&["ldap"], false, false).is_err());
+    assert!(check_ldap_transport(&["ldaps", "ldap"], false, false).is_err());
+
+    assert!(check_ldap_transport(&["ldap"], false, true).is_ok());
+    assert!(check_ldap_transport(&["ldap"], true, false).is_ok());
+    assert!(check_ldap_transport(&["LDAPS"], false, false).is_ok());
+    // A local socket
+    assert!(check_ldap_transport(&["ldapi"], false, false).is_ok());
+}
+
+#[test]
+fn test_refuses_starttls_with_ldaps() {
+    assert!(check_ldap_transport(&["ldaps"], true, false).is_err());
+    assert!(check_ldap_transport(&["ldaps"], true, true).is_err());
+}
+
+#[test]
+fn test_conn_settings_starttls() {
+    assert!(build_conn_settings(None, None, true, true).unwrap().starttls());
+    assert!(!build_conn_settings(None, None, true, false).unwrap().starttls());
+    assert!(build_conn_settings(Some("src/tests/keys/missing.pem"), None, true, false).is_err());
+}
+
+#[actix_web::test]
+async fn test_conn_settings_trust_ca_file() {
+    let (url, ca_file) = start_tls_server("localhost");
+
+    // The self-signed certificate is only trusted with the CA file
+    let untrusted = build_conn_settings(None, None, true, false).unwrap();
+    assert!(LdapConnAsync::with_settings(untrusted, &url).await.is_err());
+    let trusted = build_conn_settings(Some(&ca_file), None, true, false).unwrap();
+    assert!(LdapConnAsync::with_settings(trusted, &url).await.is_ok());
+}
+
+#[actix_web::test]
+async fn test_conn_settings_verify_hostname() {
+    let (url, ca_file) = start_tls_server("ldap.example.com");
+
+    // The certificate is trusted, but issued for another hostname
+    let verified = build_conn_settings(Some(&ca_file), None, true, false).unwrap();
+    assert!(LdapConnAsync::with_settings(verified, &url).await.is_err());
+    let unverified = build_conn_settings(Some(&ca_file), None, false, false).unwrap();
+    assert!(LdapConnAsync::with_settings(unverified, &url).await.is_ok());
+}
+
+#[test]
+fn test_read_ca_bundle() {
+    assert_eq!(read_ca_bundle("src/tests/keys/ca.pem").unwrap().len(), 1);
+}
+
+#[test]
+fn test_read_ca_bundle_without_certificates() {
+    assert!(read_ca_bundle("src/tests/keys/es256_public.pem").is_err());
+    assert!(read_ca_bundle("src/tests/keys/missing.pem").is_err());
+}
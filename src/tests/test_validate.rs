@@ -0,0 +1,133 @@
+use crate::tests::init_config;
+use crate::validate_request;
+use actix_web::{test, web, App};
+use authio::models::jwt::issue_token;
+use authio::models::Permission;
+use authio::stores::Denylist;
+use serde_json::json;
+
+#[actix_web::test]
+async fn test_validate_request_returns_identity_headers() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(validate_request),
+    )
+    .await;
+
+    let permissions: Vec<Permission> = serde_json::from_value(json!([
+        {"name": "tool1", "description": "Tool 1 Group", "access_type": "READ"},
+        {"name": "tool2", "description": "Tool 2 Group", "access_type": "WRITE"},
+    ]))
+    .unwrap();
+    let token = issue_token("tester", permissions, false).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/validate_request")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("X-Auth-User").unwrap(), "tester");
+    assert_eq!(resp.headers().get("X-Auth-Company").unwrap(), "Example AB");
+    assert_eq!(resp.headers().get("X-Auth-Permissions").unwrap(), "tool1:READ,tool2:WRITE");
+}
+
+#[actix_web::test]
+async fn test_validate_request_encodes_permission_names() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(validate_request),
+    )
+    .await;
+
+    let permissions: Vec<Permission> = serde_json::from_value(json!([
+        {"name": "x:READ,admin", "description": "", "access_type": "WRITE"},
+        {"name": "verkt\u{f6}y_1.0", "description": "", "access_type": "READ"},
+    ]))
+    .unwrap();
+    let token = issue_token("tester", permissions, false).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/validate_request")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+    assert_eq!(
+        resp.headers().get("X-Auth-Permissions").unwrap(),
+        "x%3AREAD%2Cadmin:WRITE,verkt%C3%B6y_1.0:READ"
+    );
+
+    // A user that can not be sent in a header is an error, not a response without identity
+    let token = issue_token("tester\nX-Auth-User: admin", vec![], false).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/validate_request")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 500);
+    assert!(resp.headers().get("X-Auth-User").is_none());
+}
+
+#[actix_web::test]
+async fn test_validate_request_without_token() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(validate_request),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/validate_request").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 401);
+    assert!(resp.headers().get("X-Auth-User").is_none());
+}
+
+#[actix_web::test]
+async fn test_validate_request_checks_permission() {
+    init_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Denylist::new(3600, 60)))
+            .service(validate_request),
+    )
+    .await;
+
+    let permissions: Vec<Permission> = serde_json::from_value(json!([
+        {"name": "tool1", "description": "Tool 1 Group", "access_type": "WRITE"},
+    ]))
+    .unwrap();
+    let token = issue_token("tester", permissions, false).unwrap();
+
+    for (uri, status) in [
+        ("/validate_request?permission=tool1&access=WRITE", 200),
+        ("/validate_request?permission=tool1", 200),
+        ("/validate_request?permission=tool1&access=EXECUTE", 403),
+        ("/validate_request?permission=tool2", 403),
+        ("/validate_request?permission=tool1&access=ADMIN", 400),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), status, "{}", uri);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/validate_request")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header(("X-Required-Permission", "tool2"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 403);
+}
@@ -1,5 +1,24 @@
+use crate::models::AuthError;
 use crate::traits::{Authenticate, Authorize};
+use std::future::Future;
+use std::pin::Pin;
 
 /// Trait for authentication and authorization
 /// Combines the `Authenticate` and `Authorize` traits
-pub trait Auth: Authenticate + Authorize {}
+///
+/// Connectors that talk to a backend open the connection in `connect` and close it in `disconnect`.
+/// Both default to doing nothing, for connectors without a connection.
+pub trait Auth: Authenticate + Authorize + Send {
+    /// Connect to the backend of the connector
+    ///
+    /// # Returns
+    /// A `Future` that resolves to an `AuthError` if the backend can not be used
+    fn connect(&mut self) -> Pin<Box<dyn Future<Output = Result<(), AuthError>> + Send + '_>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Close the connection to the backend
+    fn disconnect(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async {})
+    }
+}
@@ -1,6 +1,6 @@
 use std::future::Future;
 use std::pin::Pin;
-use crate::models::{Permission};
+use crate::models::{AuthError, ResolvedPermissions};
 
 pub trait Authorize {
 
@@ -15,7 +15,12 @@ pub trait Authorize {
     /// Mutably borrows `self` and a string slice `identifier`
     ///
     /// # Returns
-    /// A `Future` that resolves to a `Vec` of `Permission` objects
+    /// A `Future` that resolves to the `ResolvedPermissions` of the user, empty if the user has none and
+    /// partial if some of their groups could not be looked up. An `AuthError` if the backend could not answer,
+    /// `AuthError::UnknownUser` if the user does not exist.
     ///
-    fn resolve_permission<'a>(&'a mut self, identifier: &'a str) -> Pin<Box<dyn Future<Output = Vec<Permission>> + Send + 'a>>;
+    fn resolve_permission<'a>(
+        &'a mut self,
+        identifier: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ResolvedPermissions, AuthError>> + Send + 'a>>;
 }